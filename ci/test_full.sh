@@ -59,6 +59,7 @@ cargo nextest run --locked $NEXTEST_PROFILE --features="${FEATURES[*]}"
 # Run library documentation tests separately; cargo-nextest does not run them.
 cargo test --locked --doc
 
-# CLI smoke test intentionally omitted: dvoratt is a TUI-only binary with no
-# argument parser, so `--help` would launch the alternate-screen UI rather than
-# print help text. Re-enable once a `--help` flag exists.
+# CLI smoke test: these commands print and exit without starting the TUI.
+cargo run --locked -- --help
+cargo run --locked -- --version
+cargo run --locked -- lessons list
@@ -0,0 +1,295 @@
+//! Adaptive training that unlocks keys as they are mastered.
+//!
+//! A session starts with a few keys of the layout and only unlocks the next
+//! one once every unlocked key is typed confidently. Words are picked on the
+//! fly among those made of unlocked keys, favouring the weakest key, and
+//! made-up words fill in while too few real words are available.
+//!
+//! Keys are unlocked row by row, starting from the home row as in
+//! [`UnlockSchedule::for_layout`], and within a row from the index fingers
+//! outwards.
+
+use std::time::Duration;
+
+use rand::RngExt;
+use rand::rngs::StdRng;
+use rand::seq::IndexedRandom;
+
+use crate::keyboard_layout::{Finger, KeyboardLayout};
+use crate::lesson_generator::UnlockSchedule;
+use crate::performance::{KeyStats, KeyStatsEntry};
+
+/// Number of keys unlocked when a session starts.
+const INITIAL_KEYS: usize = 6;
+
+/// Below this many real words, made-up words are mixed in.
+const MIN_DICTIONARY_WORDS: usize = 20;
+
+/// Probability of picking a word containing the weakest key.
+const WEAKEST_KEY_BIAS: f64 = 0.7;
+
+/// What it takes for a key to be typed confidently.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct ConfidenceThreshold {
+    /// Hits needed before a key can be judged
+    pub(crate) min_hits: u32,
+    /// Highest acceptable error rate, between 0 and 1
+    pub(crate) max_error_rate: f32,
+    /// Slowest acceptable mean latency
+    pub(crate) max_latency: Duration,
+}
+
+impl Default for ConfidenceThreshold {
+    fn default() -> Self {
+        // A mean latency of 340 ms is about 35 WPM.
+        ConfidenceThreshold {
+            min_hits: 10,
+            max_error_rate: 0.05,
+            max_latency: Duration::from_millis(340),
+        }
+    }
+}
+
+impl ConfidenceThreshold {
+    /// How close the key is to the threshold, from 0 for an untyped key to 1
+    /// or more for a confident one.
+    ///
+    /// The score is the worst of the hit count, error rate and latency
+    /// ratios.
+    pub(crate) fn confidence(&self, entry: Option<&KeyStatsEntry>) -> f32 {
+        let Some(entry) = entry else {
+            return 0.0;
+        };
+        let hits = entry.hits as f32 / self.min_hits as f32;
+        let accuracy = if entry.error_rate() > 0.0 {
+            self.max_error_rate / entry.error_rate()
+        } else {
+            f32::INFINITY
+        };
+        let speed = match entry.mean_latency() {
+            Some(latency) => self.max_latency.as_secs_f32() / latency.as_secs_f32(),
+            None => 0.0,
+        };
+        hits.min(accuracy).min(speed)
+    }
+}
+
+/// The keys unlocked so far and the words that can be built from them.
+#[derive(Debug, Clone)]
+pub(crate) struct AdaptiveLesson {
+    order: Vec<char>,
+    unlocked: usize,
+    weakest: Option<char>,
+    threshold: ConfidenceThreshold,
+    dictionary: Vec<String>,
+    /// Dictionary words made only of unlocked keys
+    candidates: Vec<String>,
+}
+
+impl AdaptiveLesson {
+    /// Start a lesson on the letters of `layout`, picking words from
+    /// `dictionary`.
+    pub(crate) fn new(layout: &KeyboardLayout, dictionary: Vec<String>) -> Self {
+        let order: Vec<char> = UnlockSchedule::for_layout(layout)
+            .levels()
+            .iter()
+            .flat_map(|keys| {
+                let mut keys = keys.clone();
+                keys.sort_by_key(|&key| layout.finger(key).map(finger_rank));
+                keys
+            })
+            .collect();
+        let mut lesson = AdaptiveLesson {
+            unlocked: INITIAL_KEYS.min(order.len()),
+            order,
+            weakest: None,
+            threshold: ConfidenceThreshold::default(),
+            dictionary,
+            candidates: Vec::new(),
+        };
+        lesson.update_candidates();
+        lesson
+    }
+
+    pub(crate) fn unlocked_keys(&self) -> &[char] {
+        &self.order[..self.unlocked]
+    }
+
+    /// The key unlocked next, or `None` once every key is unlocked.
+    pub(crate) fn next_key(&self) -> Option<char> {
+        self.order.get(self.unlocked).copied()
+    }
+
+    /// The unlocked key with the lowest confidence, once known.
+    pub(crate) fn weakest_key(&self) -> Option<char> {
+        self.weakest
+    }
+
+    /// Refresh the weakest key from `stats`, unlocking the next key if every
+    /// unlocked key is confident.
+    ///
+    /// Returns the newly unlocked key, if any.
+    pub(crate) fn update(&mut self, stats: &KeyStats) -> Option<char> {
+        let threshold = self.threshold;
+        let confidence = |key: char| threshold.confidence(stats.get(key));
+        let all_confident = self
+            .unlocked_keys()
+            .iter()
+            .all(|&key| confidence(key) >= 1.0);
+        let unlocked_key = self.next_key().filter(|_| all_confident);
+        if unlocked_key.is_some() {
+            self.unlocked += 1;
+            self.update_candidates();
+        }
+        self.weakest = self
+            .unlocked_keys()
+            .iter()
+            .copied()
+            .min_by(|&a, &b| confidence(a).total_cmp(&confidence(b)));
+        unlocked_key
+    }
+
+    /// Pick the next word to type.
+    pub(crate) fn generate_word(&self, rng: &mut StdRng) -> String {
+        let use_dictionary = !self.candidates.is_empty()
+            && (self.candidates.len() >= MIN_DICTIONARY_WORDS || rng.random_bool(0.5));
+        if !use_dictionary {
+            return self.made_up_word(rng);
+        }
+
+        if let Some(weakest) = self.weakest
+            && rng.random_bool(WEAKEST_KEY_BIAS)
+        {
+            let with_weakest: Vec<&String> = self
+                .candidates
+                .iter()
+                .filter(|word| word.contains(weakest))
+                .collect();
+            if let Some(word) = with_weakest.choose(rng) {
+                return (*word).clone();
+            }
+        }
+        self.candidates.choose(rng).cloned().unwrap_or_default()
+    }
+
+    /// A random string of unlocked keys, including the weakest key if known.
+    fn made_up_word(&self, rng: &mut StdRng) -> String {
+        let keys = self.unlocked_keys();
+        let mut word: Vec<char> = (0..rng.random_range(3..=6))
+            .filter_map(|_| keys.choose(rng).copied())
+            .collect();
+        if let Some(weakest) = self.weakest
+            && !word.contains(&weakest)
+        {
+            let index = rng.random_range(0..word.len());
+            word[index] = weakest;
+        }
+        word.into_iter().collect()
+    }
+
+    fn update_candidates(&mut self) {
+        let unlocked = self.unlocked_keys();
+        self.candidates = self
+            .dictionary
+            .iter()
+            .filter(|word| !word.is_empty() && word.chars().all(|c| unlocked.contains(&c)))
+            .cloned()
+            .collect();
+    }
+}
+
+/// Fingers by decreasing dexterity: index, middle, ring, then pinky.
+fn finger_rank(finger: Finger) -> u8 {
+    match finger {
+        Finger::LeftIndex | Finger::RightIndex => 0,
+        Finger::LeftMiddle | Finger::RightMiddle => 1,
+        Finger::LeftRing | Finger::RightRing => 2,
+        Finger::LeftPinky | Finger::RightPinky => 3,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    fn dictionary(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    fn type_confidently(stats: &mut KeyStats, key: char) {
+        for _ in 0..10 {
+            stats.record(key, true, Some(Duration::from_millis(200)));
+        }
+    }
+
+    #[test]
+    fn test_unlock_order_starts_from_index_fingers() {
+        let lesson = AdaptiveLesson::new(&KeyboardLayout::default(), Vec::new());
+        assert_eq!(lesson.unlocked_keys(), ['u', 'i', 'd', 'h', 'e', 't']);
+        assert_eq!(lesson.next_key(), Some('o'));
+    }
+
+    #[test]
+    fn test_confidence() {
+        let threshold = ConfidenceThreshold::default();
+        assert_eq!(threshold.confidence(None), 0.0);
+
+        let mut stats = KeyStats::default();
+        type_confidently(&mut stats, 'a');
+        assert!(threshold.confidence(stats.get('a')) >= 1.0);
+
+        stats.record('a', false, None);
+        assert!(threshold.confidence(stats.get('a')) < 1.0);
+
+        stats.record('o', true, Some(Duration::from_millis(680)));
+        assert!(threshold.confidence(stats.get('o')) <= 0.1);
+    }
+
+    #[test]
+    fn test_unlocks_when_every_key_is_confident() {
+        let mut lesson = AdaptiveLesson::new(&KeyboardLayout::default(), Vec::new());
+        let mut stats = KeyStats::default();
+        for &key in &lesson.unlocked_keys()[..5] {
+            type_confidently(&mut stats, key);
+        }
+        assert_eq!(lesson.update(&stats), None);
+        assert_eq!(lesson.weakest_key(), Some('t'));
+
+        type_confidently(&mut stats, 't');
+        assert_eq!(lesson.update(&stats), Some('o'));
+        assert_eq!(lesson.unlocked_keys().len(), 7);
+        assert_eq!(lesson.weakest_key(), Some('o'));
+    }
+
+    #[test]
+    fn test_words_use_unlocked_keys_only() {
+        let words = dictionary(&["the", "tide", "hide", "dog", "edit", "note"]);
+        let lesson = AdaptiveLesson::new(&KeyboardLayout::default(), words);
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..50 {
+            let word = lesson.generate_word(&mut rng);
+            assert!(word.len() >= 3);
+            assert!(word.chars().all(|c| lesson.unlocked_keys().contains(&c)));
+        }
+    }
+
+    #[test]
+    fn test_words_favour_weakest_key() {
+        let mut words: Vec<String> = (0..30).map(|_| "tide".to_string()).collect();
+        words.push("hue".to_string());
+        let mut lesson = AdaptiveLesson::new(&KeyboardLayout::default(), words);
+        let mut stats = KeyStats::default();
+        for key in ['u', 'i', 'd', 'e', 't'] {
+            type_confidently(&mut stats, key);
+        }
+        lesson.update(&stats);
+        assert_eq!(lesson.weakest_key(), Some('h'));
+
+        let mut rng = StdRng::seed_from_u64(3);
+        let hue = (0..100)
+            .filter(|_| lesson.generate_word(&mut rng) == "hue")
+            .count();
+        assert!(hue > 50, "only {hue} words out of 100 had the weakest key");
+    }
+}
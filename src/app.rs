@@ -1,162 +1,378 @@
-//! Application state and core logic for the Dvorak typing practice.
+//! Application state of the Dvorak typing practice.
 //!
-//! This module contains the main `App` struct that manages the application state,
-//! including the word queue, performance tracking, and user input handling.
+//! This module contains the main `App` struct, which wraps the typing session
+//! of the [`engine`](crate::engine) with the word lists, the ghost race and the
+//! display state of the terminal interface.
+
+use anyhow::{Result, bail};
+use jiff::Timestamp;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::clock::{Clock, SystemClock};
+use crate::config::{Config, Panels};
+use crate::engine::{InputMode, Key, KeyOutcome, SessionBuilder, TypingSession, WordSource};
+use crate::ghost::GhostRace;
+use crate::history::{CombinationRecord, KeystrokeRecord, SessionRecord};
+use crate::input::{Command, InputEvent, Keymap};
+use crate::keyboard_layout::KeyboardLayout;
+use crate::performance::MasteryPolicy;
+use crate::report::{GhostRaceReport, SessionReport};
+use crate::theme::Theme;
+use crate::word_lists::{WordList, load_word_lists};
 
-use crossterm::event::KeyCode;
-use std::time::Instant;
+const DEFAULT_WORD_LIST_INDEX: usize = 1;
 
-use crate::performance::PerformanceTracker;
-use crate::word_lists::{WordList, load_word_lists};
-use crate::word_queue::WordQueue;
+/// The per-key statistic shown on the keyboard heatmap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum HeatmapMode {
+    #[default]
+    ErrorRate,
+    Latency,
+}
+
+/// Settings a new `App` starts from.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct AppOptions {
+    /// Index of the word list to start on
+    pub(crate) list_index: usize,
+    /// Seed for the word order, or `None` for a random order
+    pub(crate) seed: Option<u64>,
+    /// Word list files or directories to load after the embedded lessons
+    pub(crate) word_paths: Vec<PathBuf>,
+    /// Length of a timed test, or `None` for endless practice
+    pub(crate) time_limit: Option<Duration>,
+    /// Keyboard layout shown on screen and used for finger statistics
+    pub(crate) layout: KeyboardLayout,
+    /// Unlock keys progressively instead of practicing a word list
+    pub(crate) adaptive: bool,
+    /// Words due for review from earlier sessions, ignored in adaptive mode
+    pub(crate) review_words: Vec<String>,
+    /// When problem words are mastered
+    pub(crate) mastery: MasteryPolicy,
+    /// How wrong keys are handled
+    pub(crate) input_mode: InputMode,
+}
+
+impl Default for AppOptions {
+    fn default() -> Self {
+        AppOptions {
+            list_index: DEFAULT_WORD_LIST_INDEX,
+            seed: None,
+            word_paths: Vec::new(),
+            time_limit: None,
+            layout: KeyboardLayout::default(),
+            adaptive: false,
+            review_words: Vec::new(),
+            mastery: MasteryPolicy::default(),
+            input_mode: InputMode::default(),
+        }
+    }
+}
 
 /// The main application state.
 ///
-/// This struct contains all the state needed to run the typing practice application,
-/// including the current word queue, performance metrics, available word lists,
-/// and user input state.
-const DEFAULT_WORD_LIST_INDEX: usize = 1;
-
+/// This struct wraps the typing session with the state of the terminal
+/// interface: the available word lists, the race against a recorded session
+/// and how the session is displayed.
 pub(crate) struct App {
-    /// Performance tracking and statistics
-    pub(crate) performance: PerformanceTracker,
-    /// Queue of words to type, including problem words
-    pub(crate) word_queue: WordQueue,
+    /// The words, input and statistics of the session
+    pub(crate) session: TypingSession,
     /// Available word lists for different difficulty levels
     pub(crate) word_lists: Vec<WordList>,
     /// Index of the currently selected word list
     pub(crate) current_list_index: usize,
-    /// Current user input for the word being typed
-    pub(crate) user_input: String,
-}
-
-impl Default for App {
-    fn default() -> Self {
-        Self::new()
-    }
+    /// Statistic shown on the keyboard heatmap
+    pub(crate) heatmap_mode: HeatmapMode,
+    /// The race against a recorded session, if there is one
+    pub(crate) ghost_race: Option<GhostRace>,
+    /// Seed of the word order, if the session has one
+    pub(crate) seed: Option<u64>,
+    /// Styles the interface is drawn with
+    pub(crate) theme: Theme,
+    /// Optional panels shown while typing
+    pub(crate) panels: Panels,
+    /// Input events of the keys
+    pub(crate) keymap: Keymap,
+    /// Why the last change of the config file was rejected
+    pub(crate) config_error: Option<String>,
+    /// Settings of the config file changed since the session started, which
+    /// apply from the next session
+    pub(crate) next_session_changes: Vec<&'static str>,
+    /// Where keystrokes, ticks and the screen read the time from
+    clock: Box<dyn Clock>,
+    /// When the session was paused, if it is
+    paused_at: Option<Instant>,
+    /// Time spent paused, left out of the session time
+    paused_for: Duration,
 }
 
 impl App {
-    /// Create a new `App` instance with default settings.
-    pub(crate) fn new() -> Self {
-        let word_lists = load_word_lists();
-        let word_queue = WordQueue::new(word_lists[DEFAULT_WORD_LIST_INDEX].words.clone());
-        App {
-            performance: PerformanceTracker::default(),
-            word_queue,
-            word_lists,
-            current_list_index: DEFAULT_WORD_LIST_INDEX,
-            user_input: String::new(),
+    /// Create a new `App` instance from the given options.
+    ///
+    /// Fails if a user word list cannot be loaded or if the requested word
+    /// list does not exist.
+    pub(crate) fn new(options: AppOptions) -> Result<Self> {
+        let word_lists = load_word_lists(&options.word_paths)?;
+        let Some(word_list) = word_lists.get(options.list_index) else {
+            bail!(
+                "level {} does not exist, available levels are 1 to {}",
+                options.list_index + 1,
+                word_lists.len()
+            );
+        };
+        let source = if options.adaptive {
+            WordSource::Adaptive {
+                dictionary: word_lists
+                    .iter()
+                    .flat_map(|list| list.words.iter().cloned())
+                    .collect(),
+            }
+        } else {
+            WordSource::Words(word_list.words.clone())
+        };
+        let mut builder = SessionBuilder::new(source)
+            .layout(options.layout)
+            .review_words(options.review_words)
+            .mastery(options.mastery)
+            .input_mode(options.input_mode);
+        if let Some(seed) = options.seed {
+            builder = builder.seed(seed);
         }
+        if let Some(limit) = options.time_limit {
+            builder = builder.time_limit(limit);
+        }
+        Ok(App {
+            session: builder.build()?,
+            word_lists,
+            current_list_index: options.list_index,
+            heatmap_mode: HeatmapMode::default(),
+            ghost_race: None,
+            seed: options.seed,
+            theme: Theme::default(),
+            panels: Panels::default(),
+            keymap: Keymap::default(),
+            config_error: None,
+            next_session_changes: Vec::new(),
+            clock: Box::new(SystemClock),
+            paused_at: None,
+            paused_for: Duration::ZERO,
+        })
     }
 
-    pub(crate) fn on_key(&mut self, key: KeyCode) {
-        let current_time = Instant::now();
+    /// Read the time from `clock` instead of the system clock.
+    pub(crate) fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
 
-        if let Some(last_time) = self.performance.last_keypress_time() {
-            let duration = current_time.duration_since(last_time);
-            self.performance
-                .update_struggle_combinations(duration, &self.user_input);
-        }
+    /// The current time, according to the clock of the app.
+    pub(crate) fn now(&self) -> Instant {
+        self.clock.now()
+    }
+
+    /// The current time of the session, which stands still while it is
+    /// paused.
+    pub(crate) fn session_now(&self) -> Instant {
+        self.paused_at.unwrap_or_else(|| self.now()) - self.paused_for
+    }
+
+    pub(crate) fn is_paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+
+    /// Use the theme, panels and keys of `config`, without colours if
+    /// `NO_COLOR` is set.
+    pub(crate) fn apply_display_config(&mut self, config: &Config) {
+        self.theme = Theme::from_env(config.palette());
+        self.panels = config.panels;
+        self.keymap = config.keymap().clone();
+    }
 
-        self.performance.set_last_keypress_time(current_time);
+    /// Use the theme, panels, keys and mastery policy of `config`, and tell
+    /// whether the mastery policy changed.
+    pub(crate) fn apply_config(&mut self, config: &Config) -> bool {
+        self.apply_display_config(config);
+        let changed = self.session.mastery_policy() != config.mastery;
+        self.session.set_mastery_policy(config.mastery);
+        changed
+    }
 
-        match key {
-            KeyCode::Char(c) => {
-                self.performance.start_word_if_needed(current_time);
-                if c == ' ' {
-                    self.on_word_completed();
-                } else {
-                    let current_word = self.word_queue.current_word();
-                    if self.user_input.len() < current_word.len() {
-                        let expected_char =
-                            current_word.chars().nth(self.user_input.len()).unwrap();
-                        if c != expected_char {
-                            self.performance.record_mistype(self.user_input.len());
-                        }
-                    }
-                    self.user_input.push(c);
+    #[cfg(test)]
+    pub(crate) fn on_event(&mut self, event: InputEvent) {
+        self.handle_event(event, self.now());
+    }
+
+    /// Apply an input event at `now`, as told by the clock of the app.
+    ///
+    /// While the session is paused, every event but the one resuming it is
+    /// ignored. Ending the session is up to the caller.
+    pub(crate) fn handle_event(&mut self, event: InputEvent, now: Instant) {
+        if let Some(paused_at) = self.paused_at {
+            if event == InputEvent::Pause {
+                self.paused_for += now.saturating_duration_since(paused_at);
+                self.paused_at = None;
+            }
+            return;
+        }
+        let session_now = now - self.paused_for;
+        match event {
+            InputEvent::Type(c) => self.press(Key::Char(c), session_now),
+            InputEvent::Submit => self.press(Key::Char(' '), session_now),
+            InputEvent::DeleteChar => self.press(Key::Backspace, session_now),
+            InputEvent::DeleteWord => {
+                for _ in 0..self.session.user_input.chars().count() {
+                    self.press(Key::Backspace, session_now);
                 }
             }
-            KeyCode::Backspace if !self.user_input.is_empty() => {
-                self.user_input.pop();
-                self.performance.undo_mistype_at(self.user_input.len());
-                self.performance.record_backspace();
-                self.add_problem_word();
+            InputEvent::NextList => {
+                let next_index = (self.current_list_index + 1) % self.word_lists.len();
+                self.change_word_list(next_index);
             }
-            _ => {}
+            InputEvent::PrevList => {
+                let next_index =
+                    (self.current_list_index + self.word_lists.len() - 1) % self.word_lists.len();
+                self.change_word_list(next_index);
+            }
+            InputEvent::Pause => self.paused_at = Some(now),
+            InputEvent::Command(Command::ToggleHeatmap) => self.toggle_heatmap_mode(),
+            InputEvent::Command(Command::Restart) if self.session.is_finished() => {
+                self.restart_timed_test();
+            }
+            InputEvent::Command(Command::Restart) | InputEvent::Quit => {}
         }
     }
 
-    fn on_word_completed(&mut self) {
-        if self.user_input == self.word_queue.current_word() {
-            let speed = self.calculate_word_speed();
-            self.performance.update_recent_word_speeds(speed);
-            self.performance
-                .update_fastest_slowest_words(&self.user_input, speed);
-            self.performance
-                .record_word_completed(self.word_queue.current_word().len() as u32);
+    /// Feed `key` to the session at `now`, and move the player of the race.
+    fn press(&mut self, key: Key, now: Instant) {
+        let word_index = self.session.word_queue.word_index();
+        let outcome = self.session.press_at(key, now);
+        let Some(race) = &mut self.ghost_race else {
+            return;
+        };
+        if matches!(key, Key::Char(_)) && outcome != KeyOutcome::Ignored {
+            race.start_if_needed(now);
+        }
+        if let KeyOutcome::Completed { word, .. } = outcome {
+            race.record_word(word_index, word.chars().count(), now);
+        }
+    }
 
-            if self.word_queue.is_current_word_problem() {
-                self.word_queue.update_problem_word_correct_attempt();
-                if self.word_queue.get_current_problem_word_repetitions() >= 3 {
-                    self.performance
-                        .update_problem_word_correct_attempts(self.word_queue.current_word());
-                }
-            } else if self.performance.backspace_used() {
-                self.add_problem_word();
-            } else {
-                self.performance
-                    .update_problem_word_correct_attempts(self.word_queue.current_word());
-            }
+    pub(crate) fn toggle_heatmap_mode(&mut self) {
+        self.heatmap_mode = match self.heatmap_mode {
+            HeatmapMode::ErrorRate => HeatmapMode::Latency,
+            HeatmapMode::Latency => HeatmapMode::ErrorRate,
+        };
+    }
 
-            self.performance.remove_learned_words();
-            self.word_queue.next_word();
-        } else {
-            self.add_problem_word();
-        }
-        self.user_input.clear();
-        self.performance.reset_word_state();
+    pub(crate) fn average_speed_last_10_words(&self) -> f32 {
+        self.session.performance.average_speed_last_10_words()
     }
 
-    fn add_problem_word(&mut self) {
-        let speed = self.calculate_word_speed();
-        let current_word = self.word_queue.current_word().to_string();
-        self.performance
-            .add_problem_word(current_word.clone(), speed);
-        self.word_queue.add_problem_word(current_word);
+    /// Report the statistics of the session, ended at `timestamp`.
+    pub(crate) fn final_report(&self, timestamp: Timestamp) -> SessionReport {
+        SessionReport {
+            timestamp,
+            level: self.current_list_index + 1,
+            level_name: self.level_name(),
+            seed: self.seed,
+            scores: self.session.performance.final_scores(&self.session.layout),
+            ghost_race: self
+                .ghost_race
+                .as_ref()
+                .and_then(GhostRace::result)
+                .map(|result| GhostRaceReport {
+                    won: result.won(),
+                    words: result.words,
+                    wpm: result.player_wpm,
+                    ghost_wpm: result.ghost_wpm,
+                    wpm_delta: result.wpm_delta(),
+                    time_delta_secs: result.time_delta(),
+                }),
+        }
     }
 
-    fn calculate_word_speed(&self) -> f32 {
-        if let Some(start_time) = self.performance.word_start_time() {
-            let elapsed = start_time.elapsed();
-            let minutes = elapsed.as_secs_f32() / 60.0;
-            (self.word_queue.current_word().len() as f32 / 5.0) / minutes
-        } else {
-            0.0
+    /// Summarize the session for the persistent history, with the path of its
+    /// keystroke log.
+    pub(crate) fn session_record(
+        &self,
+        timestamp: Timestamp,
+        log_file: Option<PathBuf>,
+    ) -> SessionRecord {
+        let scores = self.session.performance.final_scores(&self.session.layout);
+        SessionRecord {
+            timestamp,
+            level: self.current_list_index + 1,
+            level_name: self.level_name(),
+            adaptive: self.session.word_queue.adaptive_lesson().is_some(),
+            average_wpm: self.session.performance.average_wpm(),
+            keystrokes: self.keystroke_record(),
+            problem_words: scores.problem_words,
+            struggle_combinations: scores
+                .struggle_combinations
+                .into_iter()
+                .map(|stats| CombinationRecord {
+                    combination: stats.combination,
+                    speed: stats.speed,
+                })
+                .collect(),
+            fastest_words: scores.fastest_words,
+            slowest_words: scores.slowest_words,
+            log_file,
+            seed: self.seed,
         }
     }
 
-    pub(crate) fn average_speed_last_10_words(&self) -> f32 {
-        self.performance.average_speed_last_10_words()
+    /// The word list name, or the unlocked keys in adaptive mode.
+    pub(crate) fn level_name(&self) -> String {
+        match self.session.word_queue.adaptive_lesson() {
+            Some(lesson) => format!(
+                "Adaptive: {}",
+                lesson.unlocked_keys().iter().collect::<String>()
+            ),
+            None => self.word_lists[self.current_list_index].name.clone(),
+        }
     }
 
-    pub(crate) fn generate_final_scores(&self) -> String {
-        self.performance.generate_final_scores()
+    fn keystroke_record(&self) -> KeystrokeRecord {
+        let results = self.session.results();
+        KeystrokeRecord {
+            accuracy: results.accuracy,
+            raw_wpm: results.raw_wpm,
+            net_wpm: results.net_wpm,
+            words_typed: results.words_typed,
+            total_keystrokes: results.total_keystrokes,
+            correct_keystrokes: results.correct_keystrokes,
+            corrected_errors: results.corrected_errors,
+            uncorrected_errors: results.uncorrected_errors,
+        }
     }
 
     pub(crate) fn on_tick(&mut self) {
-        // This method can be used for periodic updates, such as updating the timer
-        // or refreshing the struggle combinations list
+        self.session.tick_at(self.session_now());
     }
 
+    /// Start a new timed test of the same length, on the next keystroke.
+    pub(crate) fn restart_timed_test(&mut self) {
+        self.session.restart();
+        if let Some(race) = &mut self.ghost_race {
+            race.restart();
+        }
+    }
+
+    /// Switch to another word list. Adaptive lessons pick their own words, so
+    /// this does nothing in adaptive mode.
     pub(crate) fn change_word_list(&mut self, index: usize) {
-        if index < self.word_lists.len() {
+        if index < self.word_lists.len()
+            && self
+                .session
+                .change_words(self.word_lists[index].words.clone())
+        {
             self.current_list_index = index;
-            let new_words = self.word_lists[index].words.clone();
-            self.word_queue.change_word_list(new_words);
-            self.performance.reset_word_state();
-            self.user_input.clear();
+            if let Some(race) = &mut self.ghost_race {
+                race.restart();
+            }
         }
     }
 }
@@ -164,86 +380,477 @@ impl App {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crossterm::event::KeyCode;
+    use crate::clock::ManualClock;
+    use crate::performance::LearnedSpeed;
+    use crate::review::Grade;
+    use crate::session_log::LogEvent;
+
+    fn new_app() -> App {
+        App::new(AppOptions::default()).expect("default options are valid")
+    }
+
+    /// An app started from `options`, with the clock that drives it.
+    fn app_with_clock(options: AppOptions) -> (App, ManualClock) {
+        let clock = ManualClock::new();
+        let app = App::new(options).unwrap().with_clock(clock.clone());
+        (app, clock)
+    }
 
     #[test]
     fn test_app_new() {
-        let app = App::new();
+        let app = new_app();
         assert!(!app.word_lists.is_empty());
         assert_eq!(app.current_list_index, DEFAULT_WORD_LIST_INDEX);
-        assert!(app.user_input.is_empty());
-        assert!(!app.word_queue.current_word().is_empty());
+        assert!(app.session.user_input.is_empty());
+        assert!(!app.session.word_queue.current_word().is_empty());
     }
 
     #[test]
-    fn test_on_key_char() {
-        let mut app = App::new();
-        let initial_word = app.word_queue.current_word().to_string();
+    fn test_app_new_invalid_level() {
+        let options = AppOptions {
+            list_index: 99,
+            ..AppOptions::default()
+        };
+        assert!(App::new(options).is_err());
+    }
+
+    #[test]
+    fn test_app_new_same_seed_same_words() {
+        let options = AppOptions {
+            seed: Some(42),
+            ..AppOptions::default()
+        };
+        let a = App::new(options.clone()).unwrap();
+        let b = App::new(options).unwrap();
+        assert_eq!(
+            a.session.word_queue.current_word(),
+            b.session.word_queue.current_word()
+        );
+        assert_eq!(
+            a.session.word_queue.next_words(),
+            b.session.word_queue.next_words()
+        );
+        assert_eq!(a.seed, Some(42));
+        assert_eq!(a.final_report(Timestamp::UNIX_EPOCH).seed, Some(42));
+        assert_eq!(a.session_record(Timestamp::UNIX_EPOCH, None).seed, Some(42));
+    }
+
+    #[test]
+    fn test_on_event_char() {
+        let mut app = new_app();
+        let initial_word = app.session.word_queue.current_word().to_string();
 
         if let Some(c) = initial_word.chars().next() {
-            app.on_key(KeyCode::Char(c));
-            assert_eq!(app.user_input, c.to_string());
+            app.on_event(InputEvent::typed(c));
+            assert_eq!(app.session.user_input, c.to_string());
         }
     }
 
     #[test]
-    fn test_on_key_backspace() {
-        let mut app = App::new();
-        let initial_word = app.word_queue.current_word().to_string();
+    fn test_on_event_backspace() {
+        let mut app = new_app();
+        let initial_word = app.session.word_queue.current_word().to_string();
 
         if let Some(c) = initial_word.chars().next() {
-            app.on_key(KeyCode::Char(c));
-            app.on_key(KeyCode::Backspace);
-            assert!(app.user_input.is_empty());
-            assert!(app.performance.backspace_used());
+            app.on_event(InputEvent::typed(c));
+            app.on_event(InputEvent::DeleteChar);
+            assert!(app.session.user_input.is_empty());
+            assert!(app.session.performance.backspace_used());
         }
     }
 
     #[test]
-    fn test_on_key_space() {
-        let mut app = App::new();
-        let current_word = app.word_queue.current_word().to_string();
+    fn test_on_event_space() {
+        let mut app = new_app();
+        let current_word = app.session.word_queue.current_word().to_string();
 
         for c in current_word.chars() {
-            app.on_key(KeyCode::Char(c));
+            app.on_event(InputEvent::typed(c));
         }
-        app.on_key(KeyCode::Char(' '));
+        app.on_event(InputEvent::Submit);
 
-        assert!(app.user_input.is_empty());
+        assert!(app.session.user_input.is_empty());
     }
 
     #[test]
     fn test_change_word_list() {
-        let mut app = App::new();
-        let original_word = app.word_queue.current_word().to_string();
+        let mut app = new_app();
+        let original_word = app.session.word_queue.current_word().to_string();
 
         if app.word_lists.len() > 1 {
             app.change_word_list(0);
             assert_eq!(app.current_list_index, 0);
-            assert_ne!(app.word_queue.current_word(), original_word);
-            assert!(app.user_input.is_empty());
+            assert_ne!(app.session.word_queue.current_word(), original_word);
+            assert!(app.session.user_input.is_empty());
         }
     }
 
     #[test]
     fn test_average_speed_last_10_words() {
-        let mut app = App::new();
+        let (mut app, clock) = app_with_clock(AppOptions::default());
         assert_eq!(app.average_speed_last_10_words(), 0.0);
 
-        // Type a word to generate a non-zero speed
-        let word = app.word_queue.current_word().to_string();
+        // One key every 200 ms, five characters a word: 60 WPM.
+        let word = app.session.word_queue.current_word().to_string();
+        for c in word.chars().chain([' ']) {
+            app.on_event(InputEvent::typed(c));
+            clock.advance(Duration::from_millis(200));
+        }
+        let speed = app.average_speed_last_10_words();
+        assert!((speed - 60.0).abs() < 1e-3, "{speed} WPM");
+    }
+
+    #[test]
+    fn test_keystroke_accuracy() {
+        let mut app = new_app();
+        let word = app.session.word_queue.current_word().to_string();
+        let wrong = if word.starts_with('z') { 'q' } else { 'z' };
+        app.on_event(InputEvent::typed(wrong));
+        app.on_event(InputEvent::DeleteChar);
+        for c in word.chars() {
+            app.on_event(InputEvent::typed(c));
+        }
+        app.on_event(InputEvent::Submit);
+
+        let stats = app.session.performance.keystroke_stats();
+        // One wrong letter, the word itself and the submitting space.
+        assert_eq!(stats.total_keystrokes, word.chars().count() as u32 + 2);
+        assert_eq!(stats.correct_keystrokes, word.chars().count() as u32 + 1);
+        assert_eq!(stats.corrected_errors, 1);
+        assert_eq!(stats.uncorrected_errors, 0);
+        assert_eq!(stats.words_typed, 1);
+    }
+
+    #[test]
+    fn test_key_stats_use_expected_char() {
+        let (mut app, clock) = app_with_clock(AppOptions::default());
+        let word = app.session.word_queue.current_word().to_string();
+        let expected = word.chars().next().unwrap();
+        let wrong = if expected == 'z' { 'q' } else { 'z' };
+
+        app.on_event(InputEvent::typed(wrong));
+        clock.advance(Duration::from_millis(100));
+        app.on_event(InputEvent::DeleteChar);
+        clock.advance(Duration::from_millis(150));
+        app.on_event(InputEvent::typed(expected));
+
+        let keys: Vec<_> = app.session.performance.key_stats().iter().collect();
+        assert_eq!(keys.len(), 1);
+        let (key, entry) = keys[0];
+        assert_eq!(key, expected);
+        assert_eq!((entry.hits, entry.misses), (1, 1));
+        // The first key has no latency, the second is timed from Backspace.
+        assert_eq!(entry.mean_latency(), Some(Duration::from_millis(150)));
+    }
+
+    #[test]
+    fn test_next_expected_char() {
+        let mut app = new_app();
+        let word: Vec<char> = app.session.word_queue.current_word().chars().collect();
+        for c in word {
+            assert_eq!(app.session.next_char(), Some(c));
+            app.on_event(InputEvent::typed(c));
+        }
+        assert_eq!(app.session.next_char(), None);
+    }
+
+    #[test]
+    fn test_toggle_heatmap_mode() {
+        let mut app = new_app();
+        assert_eq!(app.heatmap_mode, HeatmapMode::ErrorRate);
+        app.toggle_heatmap_mode();
+        assert_eq!(app.heatmap_mode, HeatmapMode::Latency);
+        app.toggle_heatmap_mode();
+        assert_eq!(app.heatmap_mode, HeatmapMode::ErrorRate);
+    }
+
+    #[test]
+    fn test_delete_word() {
+        let mut app = new_app();
+        for c in "xqz".chars() {
+            app.on_event(InputEvent::Type(c));
+        }
+        app.on_event(InputEvent::DeleteWord);
+        assert!(app.session.user_input.is_empty());
+        assert_eq!(
+            app.session.performance.keystroke_stats().corrected_errors,
+            3
+        );
+    }
+
+    #[test]
+    fn test_stop_on_letter_rejects_wrong_keys() {
+        let mut app = App::new(AppOptions {
+            input_mode: InputMode::StopOnLetter,
+            ..AppOptions::default()
+        })
+        .unwrap();
+        let first = app
+            .session
+            .word_queue
+            .current_word()
+            .chars()
+            .next()
+            .unwrap();
+        let wrong = if first == 'x' { 'z' } else { 'x' };
+        app.on_event(InputEvent::Type(wrong));
+        assert!(app.session.user_input.is_empty());
+        app.on_event(InputEvent::Type(first));
+        assert_eq!(app.session.user_input, first.to_string());
+    }
+
+    #[test]
+    fn test_pause_stops_the_session_clock() {
+        let (mut app, clock) = app_with_clock(AppOptions {
+            time_limit: Some(Duration::from_secs(15)),
+            ..AppOptions::default()
+        });
+        let word: Vec<char> = app.session.word_queue.current_word().chars().collect();
+        app.on_event(InputEvent::Type(word[0]));
+        clock.advance(Duration::from_secs(5));
+        app.on_event(InputEvent::Pause);
+        assert!(app.is_paused());
+
+        // Keys are ignored and time stands still while paused.
+        clock.advance(Duration::from_secs(60));
+        app.on_event(InputEvent::Type(word[1]));
+        app.on_tick();
+        assert_eq!(app.session.user_input, word[0].to_string());
+        assert!(!app.session.is_finished());
+
+        app.on_event(InputEvent::Pause);
+        assert!(!app.is_paused());
+        clock.advance(Duration::from_millis(9_999));
+        app.on_tick();
+        assert!(!app.session.is_finished());
+        clock.advance(Duration::from_millis(1));
+        app.on_tick();
+        assert!(app.session.is_finished());
+    }
+
+    #[test]
+    fn test_timed_test_locks_input_when_finished() {
+        let (mut app, clock) = app_with_clock(AppOptions {
+            time_limit: Some(Duration::from_secs(15)),
+            ..AppOptions::default()
+        });
+        let word = app.session.word_queue.current_word().to_string();
+        let first = word.chars().next().unwrap();
+
+        // The test starts on the first key, not when the app is created.
+        clock.advance(Duration::from_secs(5));
+        app.on_event(InputEvent::typed(first));
+        clock.advance(Duration::from_millis(14_999));
+        app.on_tick();
+        assert!(!app.session.is_finished());
+        clock.advance(Duration::from_millis(1));
+        app.on_tick();
+        assert!(app.session.is_finished());
+
+        app.on_event(InputEvent::typed(first));
+        assert_eq!(app.session.user_input, first.to_string());
+
+        app.restart_timed_test();
+        assert!(!app.session.is_finished());
+        assert!(app.session.user_input.is_empty());
+    }
+
+    #[test]
+    fn test_session_record() {
+        let mut app = new_app();
+        let word = app.session.word_queue.current_word().to_string();
+        for c in word.chars() {
+            app.on_event(InputEvent::typed(c));
+        }
+        app.on_event(InputEvent::Submit);
+
+        let record = app.session_record(Timestamp::UNIX_EPOCH, None);
+        assert_eq!(record.level, DEFAULT_WORD_LIST_INDEX + 1);
+        assert_eq!(
+            record.level_name,
+            app.word_lists[DEFAULT_WORD_LIST_INDEX].name
+        );
+        assert_eq!(record.fastest_words[0].word, word);
+        assert_eq!(record.keystrokes.words_typed, 1);
+    }
+
+    #[test]
+    fn test_adaptive_mode() {
+        let options = AppOptions {
+            adaptive: true,
+            seed: Some(1),
+            ..AppOptions::default()
+        };
+        let mut app = App::new(options).unwrap();
+        let unlocked = app
+            .session
+            .word_queue
+            .adaptive_lesson()
+            .unwrap()
+            .unlocked_keys();
+        assert!(
+            app.session
+                .word_queue
+                .current_word()
+                .chars()
+                .all(|c| unlocked.contains(&c))
+        );
+
+        let word = app.session.word_queue.current_word().to_string();
+        for c in word.chars() {
+            app.on_event(InputEvent::typed(c));
+        }
+        app.on_event(InputEvent::Submit);
+        assert!(
+            app.session
+                .word_queue
+                .adaptive_lesson()
+                .unwrap()
+                .weakest_key()
+                .is_some()
+        );
+
+        app.change_word_list(0);
+        assert_eq!(app.current_list_index, DEFAULT_WORD_LIST_INDEX);
+        let record = app.session_record(Timestamp::UNIX_EPOCH, None);
+        assert_eq!(record.level_name, "Adaptive: uidhet");
+        assert!(record.adaptive);
+    }
+
+    #[test]
+    fn test_review_grades() {
+        let options = AppOptions {
+            review_words: vec!["heed".to_string(), "thud".to_string()],
+            ..AppOptions::default()
+        };
+        let mut app = App::new(options).unwrap();
+        assert_eq!(app.session.word_queue.current_word(), "heed");
+
+        for c in "heed ".chars() {
+            app.on_event(InputEvent::typed(c));
+        }
+        assert!(matches!(
+            app.session.review_grades().get("heed"),
+            Some(Grade::Good | Grade::Hard)
+        ));
+
+        let word = app.session.word_queue.current_word().to_string();
+        app.on_event(InputEvent::Type('#'));
+        app.on_event(InputEvent::DeleteChar);
         for c in word.chars() {
-            app.on_key(KeyCode::Char(c));
+            app.on_event(InputEvent::typed(c));
+        }
+        app.on_event(InputEvent::Submit);
+        assert_eq!(app.session.review_grades().get(&word), Some(&Grade::Again));
+        assert_eq!(app.session.review_grades().get("thud"), None);
+    }
+
+    #[test]
+    fn test_backspace_does_not_fail() {
+        let mut app = App::new(AppOptions {
+            mastery: MasteryPolicy {
+                backspace_fails: false,
+                ..MasteryPolicy::default()
+            },
+            ..AppOptions::default()
+        })
+        .unwrap();
+        let word = app.session.word_queue.current_word().to_string();
+        app.on_event(InputEvent::Type('#'));
+        app.on_event(InputEvent::DeleteChar);
+        for c in word.chars().chain([' ']) {
+            app.on_event(InputEvent::typed(c));
+        }
+        assert!(app.session.performance.get_problem_words().is_empty());
+        assert_ne!(app.session.word_queue.current_word(), word);
+    }
+
+    #[test]
+    fn test_mastery_relative_to_average() {
+        let mastered_at = |speed| {
+            let mut app = App::new(AppOptions {
+                mastery: MasteryPolicy {
+                    repetitions: 1,
+                    streak: 1,
+                    speed,
+                    ..MasteryPolicy::default()
+                },
+                ..AppOptions::default()
+            })
+            .unwrap();
+            let word = app.session.word_queue.current_word().to_string();
+            let start = Instant::now();
+            app.handle_event(InputEvent::Type('#'), start);
+            app.handle_event(InputEvent::Submit, start + Duration::from_secs(10));
+            // Typed correctly, faster than the failed attempt but slower than
+            // four times the session average.
+            for (i, c) in word.chars().chain([' ']).enumerate() {
+                app.handle_event(
+                    InputEvent::typed(c),
+                    start + Duration::from_millis(11_000 + 100 * i as u64),
+                );
+            }
+            app.session.performance.get_problem_words().is_empty()
+        };
+        assert!(mastered_at(LearnedSpeed::PercentOfAverage(10.0)));
+        assert!(!mastered_at(LearnedSpeed::PercentOfAverage(400.0)));
+    }
+
+    #[test]
+    fn test_adaptive_mode_skips_reviews() {
+        let options = AppOptions {
+            adaptive: true,
+            review_words: vec!["xylophone".to_string()],
+            ..AppOptions::default()
+        };
+        let app = App::new(options).unwrap();
+        assert_ne!(app.session.word_queue.current_word(), "xylophone");
+    }
+
+    #[test]
+    fn test_ghost_race() {
+        let mut app = new_app();
+        let word = app.session.word_queue.current_word().to_string();
+        let ghost_events: Vec<LogEvent> = [(0, word.as_str()), (5_000_000, "")]
+            .into_iter()
+            .map(|(at_us, input)| LogEvent {
+                at_us,
+                event: InputEvent::typed(input.chars().next().unwrap_or(' ')),
+                expected: word.clone(),
+                word: 0,
+                input: input.to_string(),
+                mastery: None,
+            })
+            .collect();
+        app.ghost_race = Some(GhostRace::from_events(&ghost_events).unwrap());
+
+        let start = Instant::now();
+        for (i, c) in word.chars().chain([' ']).enumerate() {
+            app.handle_event(
+                InputEvent::typed(c),
+                start + Duration::from_millis(100 * i as u64),
+            );
         }
-        app.on_key(KeyCode::Char(' '));
-        assert!(app.average_speed_last_10_words() > 0.0);
+        let race = app.ghost_race.as_ref().unwrap();
+        assert!(race.is_finished());
+        assert!(race.result().unwrap().won());
+        let report = app.final_report(Timestamp::UNIX_EPOCH).ghost_race.unwrap();
+        assert!(report.won);
+        assert_eq!(report.words, race.total_words());
+
+        app.restart_timed_test();
+        assert_eq!(app.ghost_race.as_ref().unwrap().player_words(), 0);
     }
 
     #[test]
-    fn test_generate_final_scores() {
-        let app = App::new();
-        let scores = app.generate_final_scores();
-        assert!(!scores.is_empty());
-        assert!(scores.contains("average_speed"));
+    fn test_final_report() {
+        let app = new_app();
+        let report = app.final_report(Timestamp::UNIX_EPOCH);
+        assert_eq!(report.level, DEFAULT_WORD_LIST_INDEX + 1);
+        assert_eq!(report.level_name, app.level_name());
+        assert_eq!(report.scores.layout, "Dvorak");
+        assert!(report.ghost_race.is_none());
+        let json = serde_json::to_string(&report).unwrap();
+        assert!(json.contains("average_speed"));
     }
 }
@@ -0,0 +1,385 @@
+//! Command-line interface definition.
+//!
+//! Running `dvoratt` without a subcommand starts a practice session with the
+//! default settings, which is equivalent to `dvoratt practice`.
+
+use clap::{Args, Parser, Subcommand};
+use jiff::civil::Date;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+
+use crate::engine::InputMode;
+use crate::keyboard_layout::KeyboardLayout;
+use crate::report::ExportFormat;
+use crate::timed_test::TEST_DURATIONS;
+
+/// Dvorak typing practice in the terminal.
+#[derive(Debug, Parser)]
+#[command(name = "dvoratt", version, about, long_about = None)]
+pub(crate) struct Cli {
+    #[command(subcommand)]
+    pub(crate) command: Option<Command>,
+
+    /// Extra word list file or directory (`.txt` or `.txt.gz`, one word per
+    /// line). May be repeated.
+    #[arg(short, long = "words", value_name = "PATH", global = true)]
+    pub(crate) word_paths: Vec<PathBuf>,
+}
+
+#[derive(Debug, Subcommand)]
+pub(crate) enum Command {
+    /// Start an interactive practice session (default).
+    Practice(PracticeArgs),
+    /// Inspect the available lessons.
+    #[command(subcommand)]
+    Lessons(LessonsCommand),
+    /// Replay a recorded practice session.
+    Replay(ReplayArgs),
+    /// Show statistics over past sessions.
+    Stats,
+    /// Export past sessions.
+    Export(ExportArgs),
+}
+
+/// Options controlling how a practice session starts.
+#[derive(Debug, Default, Args)]
+pub(crate) struct PracticeArgs {
+    /// Lesson level to start on (1-based, see `dvoratt lessons list`).
+    #[arg(short, long, value_parser = clap::value_parser!(u16).range(1..))]
+    pub(crate) level: Option<u16>,
+
+    /// Seed for the word order, to replay the exact same sequence. Words due
+    /// for review are left out, so that the same seed gives everyone the same
+    /// test.
+    #[arg(short, long)]
+    pub(crate) seed: Option<u64>,
+
+    /// Run a timed test of this many seconds instead of endless practice.
+    #[arg(short, long, value_name = "SECONDS", value_parser = parse_test_duration)]
+    pub(crate) time: Option<u64>,
+
+    /// Keyboard layout: dvorak, programmer-dvorak, colemak, colemak-dh,
+    /// workman, qwerty, or the path of a layout file.
+    #[arg(long, value_name = "NAME|PATH", value_parser = KeyboardLayout::resolve)]
+    pub(crate) layout: Option<KeyboardLayout>,
+
+    /// Start with a few keys and unlock the next one once every unlocked key
+    /// is typed confidently, instead of practicing a lesson level.
+    #[arg(short, long, conflicts_with = "level")]
+    pub(crate) adaptive: bool,
+
+    /// How wrong keys are handled: typed and judged on Space, rejected until
+    /// the right key is pressed, or typed but refusing Space until the word
+    /// is fixed.
+    #[arg(long, value_name = "MODE")]
+    pub(crate) input_mode: Option<InputMode>,
+
+    /// Race the ghost of a recorded session, or of the fastest recorded
+    /// session with `best`, on the same words. Adaptive sessions cannot be
+    /// raced.
+    #[arg(
+        short,
+        long,
+        value_name = "FILE|best",
+        value_parser = parse_ghost,
+        conflicts_with_all = ["level", "seed", "time", "layout", "adaptive", "input_mode"]
+    )]
+    pub(crate) ghost: Option<GhostSource>,
+
+    /// Print the statistics of each finished session as JSON on exit.
+    #[arg(long)]
+    pub(crate) json: bool,
+
+    /// Write the statistics of each finished session as a JSON report to this
+    /// file, updated after every session.
+    #[arg(short, long, value_name = "PATH")]
+    pub(crate) output: Option<PathBuf>,
+}
+
+/// The session a ghost race replays.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum GhostSource {
+    /// The fastest session of the history that has a keystroke log
+    Best,
+    /// A session log file
+    Log(PathBuf),
+}
+
+fn parse_ghost(value: &str) -> Result<GhostSource, String> {
+    Ok(match value {
+        "best" => GhostSource::Best,
+        path => GhostSource::Log(PathBuf::from(path)),
+    })
+}
+
+fn parse_test_duration(value: &str) -> Result<u64, String> {
+    let secs = value
+        .parse()
+        .map_err(|_| format!("`{value}` is not a number of seconds"))?;
+    if TEST_DURATIONS.contains(&secs) {
+        Ok(secs)
+    } else {
+        let supported: Vec<String> = TEST_DURATIONS.iter().map(u64::to_string).collect();
+        Err(format!("supported durations are {}", supported.join(", ")))
+    }
+}
+
+/// Options of `dvoratt replay`.
+#[derive(Debug, Args)]
+pub(crate) struct ReplayArgs {
+    /// Session log to replay, from the `dvoratt/sessions` data directory.
+    #[arg(value_name = "FILE")]
+    pub(crate) file: PathBuf,
+
+    /// Playback speed, relative to the recorded timing, from 0.01 to 1000.
+    #[arg(long, default_value_t = 1.0, value_parser = parse_speed)]
+    pub(crate) speed: f64,
+
+    /// Print the statistics of the replayed session as JSON on exit.
+    #[arg(long)]
+    pub(crate) json: bool,
+}
+
+/// Playback speeds of `dvoratt replay`, which keep the scaled times in range.
+const REPLAY_SPEEDS: RangeInclusive<f64> = 0.01..=1000.0;
+
+fn parse_speed(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(speed) if REPLAY_SPEEDS.contains(&speed) => Ok(speed),
+        Ok(_) => Err(format!(
+            "`{value}` is out of range, the speed is from {} to {}",
+            REPLAY_SPEEDS.start(),
+            REPLAY_SPEEDS.end()
+        )),
+        Err(_) => Err(format!("`{value}` is not a speed")),
+    }
+}
+
+/// Options of `dvoratt export`.
+#[derive(Debug, Args)]
+pub(crate) struct ExportArgs {
+    /// Output format.
+    #[arg(short, long, value_enum, default_value_t = ExportFormat::Json)]
+    pub(crate) format: ExportFormat,
+
+    /// Only export the sessions from this date on (`YYYY-MM-DD`, local time).
+    #[arg(long, value_name = "DATE")]
+    pub(crate) since: Option<Date>,
+
+    /// Write the export to this file instead of the standard output.
+    #[arg(short, long, value_name = "PATH")]
+    pub(crate) output: Option<PathBuf>,
+}
+
+#[derive(Debug, Subcommand)]
+pub(crate) enum LessonsCommand {
+    /// List the lesson levels and their word counts.
+    List,
+    /// Generate progressive lessons from a dictionary.
+    Generate(GenerateArgs),
+}
+
+/// Options of `dvoratt lessons generate`.
+#[derive(Debug, Args)]
+pub(crate) struct GenerateArgs {
+    /// Dictionary to pick words from (`.txt` or `.txt.gz`, one word per line).
+    #[arg(short, long, value_name = "FILE")]
+    pub(crate) dictionary: PathBuf,
+
+    /// Directory receiving one `level<N>.txt.gz` file per level.
+    #[arg(short, long, value_name = "DIR")]
+    pub(crate) output: PathBuf,
+
+    /// Keyboard layout whose keys are unlocked, as for `practice --layout`.
+    #[arg(long, value_name = "NAME|PATH", value_parser = KeyboardLayout::resolve)]
+    pub(crate) layout: Option<KeyboardLayout>,
+
+    /// Keys introduced at each level, as comma-separated groups such as
+    /// `aoeu,idhtns,pyf`. Defaults to the home row, then the rows above and
+    /// below.
+    #[arg(long, value_name = "KEYS,...")]
+    pub(crate) schedule: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_cli_definition() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn test_parse_practice_args() {
+        let cli = Cli::try_parse_from(["dvoratt", "practice", "--level", "3", "--seed", "42"])
+            .expect("valid arguments");
+        let Some(Command::Practice(args)) = cli.command else {
+            panic!("expected the practice subcommand");
+        };
+        assert_eq!(args.level, Some(3));
+        assert_eq!(args.seed, Some(42));
+        assert!(!args.json);
+        assert_eq!(args.output, None);
+    }
+
+    #[test]
+    fn test_parse_export() {
+        let cli = Cli::try_parse_from([
+            "dvoratt",
+            "export",
+            "--format",
+            "md",
+            "--since",
+            "2026-09-01",
+            "-o",
+            "out.md",
+        ])
+        .expect("valid arguments");
+        let Some(Command::Export(args)) = cli.command else {
+            panic!("expected the export subcommand");
+        };
+        assert_eq!(args.format, ExportFormat::Markdown);
+        assert_eq!(args.since, Some(jiff::civil::date(2026, 9, 1)));
+        assert_eq!(args.output, Some(PathBuf::from("out.md")));
+
+        let cli = Cli::try_parse_from(["dvoratt", "export"]).expect("valid arguments");
+        let Some(Command::Export(args)) = cli.command else {
+            panic!("expected the export subcommand");
+        };
+        assert_eq!(args.format, ExportFormat::Json);
+        assert_eq!(args.since, None);
+        assert!(Cli::try_parse_from(["dvoratt", "export", "--format", "xml"]).is_err());
+        assert!(Cli::try_parse_from(["dvoratt", "export", "--since", "yesterday"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let cli = Cli::try_parse_from(["dvoratt", "practice", "--json"]).expect("valid arguments");
+        let Some(Command::Practice(args)) = cli.command else {
+            panic!("expected the practice subcommand");
+        };
+        assert!(args.json);
+    }
+
+    #[test]
+    fn test_no_subcommand() {
+        let cli = Cli::try_parse_from(["dvoratt"]).expect("valid arguments");
+        assert!(cli.command.is_none());
+    }
+
+    #[test]
+    fn test_parse_word_paths() {
+        let cli = Cli::try_parse_from([
+            "dvoratt", "lessons", "list", "--words", "a.txt", "-w", "lists",
+        ])
+        .expect("valid arguments");
+        assert_eq!(
+            cli.word_paths,
+            [PathBuf::from("a.txt"), PathBuf::from("lists")]
+        );
+    }
+
+    #[test]
+    fn test_parse_test_duration() {
+        let cli =
+            Cli::try_parse_from(["dvoratt", "practice", "--time", "60"]).expect("valid arguments");
+        let Some(Command::Practice(args)) = cli.command else {
+            panic!("expected the practice subcommand");
+        };
+        assert_eq!(args.time, Some(60));
+        assert!(Cli::try_parse_from(["dvoratt", "practice", "--time", "45"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_layout() {
+        let cli = Cli::try_parse_from(["dvoratt", "practice", "--layout", "colemak-dh"])
+            .expect("valid arguments");
+        let Some(Command::Practice(args)) = cli.command else {
+            panic!("expected the practice subcommand");
+        };
+        assert_eq!(args.layout.unwrap().name, "Colemak-DH");
+        assert!(Cli::try_parse_from(["dvoratt", "practice", "--layout", "azerty"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_lessons_generate() {
+        let cli = Cli::try_parse_from([
+            "dvoratt",
+            "lessons",
+            "generate",
+            "--dictionary",
+            "words.txt",
+            "--output",
+            "out",
+            "--schedule",
+            "aoeu,idhtns",
+        ])
+        .expect("valid arguments");
+        let Some(Command::Lessons(LessonsCommand::Generate(args))) = cli.command else {
+            panic!("expected the lessons generate subcommand");
+        };
+        assert_eq!(args.dictionary, PathBuf::from("words.txt"));
+        assert_eq!(args.output, PathBuf::from("out"));
+        assert_eq!(args.schedule.as_deref(), Some("aoeu,idhtns"));
+        assert!(args.layout.is_none());
+        assert!(Cli::try_parse_from(["dvoratt", "lessons", "generate", "-o", "out"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_adaptive() {
+        let cli =
+            Cli::try_parse_from(["dvoratt", "practice", "--adaptive"]).expect("valid arguments");
+        let Some(Command::Practice(args)) = cli.command else {
+            panic!("expected the practice subcommand");
+        };
+        assert!(args.adaptive);
+        assert!(Cli::try_parse_from(["dvoratt", "practice", "-a", "--level", "2"]).is_err());
+    }
+
+    #[test]
+    fn test_level_zero_rejected() {
+        assert!(Cli::try_parse_from(["dvoratt", "practice", "--level", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_ghost() {
+        let cli = Cli::try_parse_from(["dvoratt", "practice", "--ghost", "best"])
+            .expect("valid arguments");
+        let Some(Command::Practice(args)) = cli.command else {
+            panic!("expected the practice subcommand");
+        };
+        assert_eq!(args.ghost, Some(GhostSource::Best));
+
+        let cli = Cli::try_parse_from(["dvoratt", "practice", "-g", "run.jsonl"])
+            .expect("valid arguments");
+        let Some(Command::Practice(args)) = cli.command else {
+            panic!("expected the practice subcommand");
+        };
+        assert_eq!(
+            args.ghost,
+            Some(GhostSource::Log(PathBuf::from("run.jsonl")))
+        );
+        assert!(
+            Cli::try_parse_from(["dvoratt", "practice", "--ghost", "best", "--seed", "1"]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_replay() {
+        let cli = Cli::try_parse_from(["dvoratt", "replay", "session.jsonl", "--speed", "2.5"])
+            .expect("valid arguments");
+        let Some(Command::Replay(args)) = cli.command else {
+            panic!("expected the replay subcommand");
+        };
+        assert_eq!(args.file, PathBuf::from("session.jsonl"));
+        assert_eq!(args.speed, 2.5);
+        for speed in ["0", "1e-300", "1e300", "inf", "fast"] {
+            assert!(
+                Cli::try_parse_from(["dvoratt", "replay", "s.jsonl", "--speed", speed]).is_err()
+            );
+        }
+        assert!(Cli::try_parse_from(["dvoratt", "replay"]).is_err());
+    }
+}
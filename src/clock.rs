@@ -0,0 +1,80 @@
+//! The source of time of the application.
+//!
+//! Keystrokes, ticks and the screen read the time from a [`Clock`] rather
+//! than from `Instant::now()`, so that tests and replays can control it: a
+//! [`ManualClock`] only moves when told to, which makes speeds, struggle
+//! combinations and timed tests exact.
+
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::Instant;
+
+/// A source of the current time.
+pub(crate) trait Clock {
+    fn now(&self) -> Instant;
+}
+
+/// The system monotonic clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock which stands still until it is moved.
+///
+/// Clones share the same time, so a test can keep one to move the clock it
+/// gave to an `App`.
+#[derive(Debug, Clone)]
+pub(crate) struct ManualClock {
+    now: Rc<Cell<Instant>>,
+}
+
+impl ManualClock {
+    /// A clock stopped at the current time.
+    pub(crate) fn new() -> Self {
+        ManualClock {
+            now: Rc::new(Cell::new(Instant::now())),
+        }
+    }
+
+    /// Move the clock forward by `duration`.
+    #[cfg(test)]
+    pub(crate) fn advance(&self, duration: std::time::Duration) {
+        self.now.set(self.now.get() + duration);
+    }
+
+    /// Move the clock to `now`.
+    pub(crate) fn set(&self, now: Instant) {
+        self.now.set(now);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.now.get()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_manual_clock_clones_share_time() {
+        let clock = ManualClock::new();
+        let shared = clock.clone();
+        let start = clock.now();
+        assert_eq!(clock.now(), start);
+
+        shared.advance(Duration::from_millis(250));
+        assert_eq!(clock.now(), start + Duration::from_millis(250));
+
+        clock.set(start);
+        assert_eq!(shared.now(), start);
+    }
+}
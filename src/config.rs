@@ -0,0 +1,439 @@
+//! User configuration, reloaded while the application runs.
+//!
+//! The configuration is a TOML file in the user config directory
+//! (`$XDG_CONFIG_HOME/dvoratt/config.toml` on Linux). Every key is optional, and
+//! command-line options take precedence over it. The file is polled while a
+//! session runs: the theme, panels, keys and the mastery policy apply right away,
+//! and the options a session starts from apply to the next session, which the
+//! typing screen tells.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+
+use crate::engine::InputMode;
+use crate::input::{KeyBindings, Keymap};
+use crate::keyboard_layout::KeyboardLayout;
+use crate::performance::MasteryPolicy;
+use crate::theme::{Palette, ThemeColors};
+use crate::timed_test::TEST_DURATIONS;
+
+/// The settings of the configuration file.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Config {
+    /// Lesson level to start on, counted from 1
+    pub(crate) level: Option<u16>,
+    /// Built-in layout name or layout file, relative to the config directory
+    #[serde(rename = "layout")]
+    layout_name: Option<String>,
+    /// Seed for the word order, which leaves reviews out as `--seed` does
+    pub(crate) seed: Option<u64>,
+    /// Length of a timed test in seconds, or `None` for endless practice
+    pub(crate) time: Option<u64>,
+    /// Practice an adaptive lesson instead of a level
+    pub(crate) adaptive: bool,
+    /// How wrong keys are handled, `free` by default
+    pub(crate) input_mode: Option<InputMode>,
+    pub(crate) mastery: MasteryPolicy,
+    /// Built-in or user theme, `dark` by default
+    theme: Option<String>,
+    /// User themes, by name
+    themes: BTreeMap<String, ThemeColors>,
+    /// Colours replacing those of the theme
+    colors: ThemeColors,
+    pub(crate) panels: Panels,
+    /// Keys bound to each action
+    keys: KeyBindings,
+    /// The layout resolved from `layout_name`
+    #[serde(skip)]
+    layout: Option<KeyboardLayout>,
+    /// The palette resolved from `theme`, `themes` and `colors`
+    #[serde(skip)]
+    palette: Palette,
+    /// The keymap resolved from `keys`
+    #[serde(skip)]
+    keymap: Keymap,
+}
+
+/// The settings a session starts from, which a reload leaves to the next
+/// session.
+type SessionSetting = (&'static str, fn(&Config, &Config) -> bool);
+
+const SESSION_SETTINGS: [SessionSetting; 6] = [
+    ("level", |a, b| a.level == b.level),
+    ("layout", |a, b| a.layout == b.layout),
+    ("seed", |a, b| a.seed == b.seed),
+    ("time", |a, b| a.time == b.time),
+    ("adaptive", |a, b| a.adaptive == b.adaptive),
+    ("input_mode", |a, b| a.input_mode == b.input_mode),
+];
+
+impl Config {
+    /// Load the configuration file at `path`. A missing file is the default
+    /// configuration.
+    pub(crate) fn load(path: &Path) -> Result<Self> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Config::default());
+            }
+            Err(err) => {
+                return Err(err).with_context(|| format!("failed to read {}", path.display()));
+            }
+        };
+        let dir = path.parent().unwrap_or(Path::new(""));
+        Self::parse(&contents, dir)
+            .with_context(|| format!("invalid config file {}", path.display()))
+    }
+
+    /// Parse a configuration, with layout files relative to `dir`.
+    fn parse(contents: &str, dir: &Path) -> Result<Self> {
+        let mut config: Config = toml::from_str(contents)?;
+        if config.level == Some(0) {
+            bail!("levels are counted from 1");
+        }
+        if config.level.is_some() && config.adaptive {
+            bail!("`level` and `adaptive` cannot both be set");
+        }
+        if let Some(secs) = config.time
+            && !TEST_DURATIONS.contains(&secs)
+        {
+            bail!("`time` must be one of {TEST_DURATIONS:?} seconds, not {secs}");
+        }
+        if config.mastery.repetitions == 0 {
+            bail!("problem words must be repeated at least once");
+        }
+        if let Some(name) = &config.layout_name {
+            let layout = match KeyboardLayout::builtin(name) {
+                Some(layout) => layout,
+                None => KeyboardLayout::resolve(&dir.join(name).to_string_lossy())?,
+            };
+            config.layout = Some(layout);
+        }
+        if config.colors.base.is_some() {
+            bail!("`colors` has no `base`, set `theme` instead");
+        }
+        for name in config.themes.keys() {
+            if Palette::builtin(name).is_some() {
+                bail!("theme `{name}` is built in, give yours another name");
+            }
+        }
+        let theme = config.theme.as_deref().unwrap_or("dark");
+        config.palette =
+            resolve_palette(theme, &config.themes, &mut Vec::new())?.with(&config.colors);
+        config.keymap = Keymap::new(config.keys.clone()).context("invalid `keys`")?;
+        Ok(config)
+    }
+
+    /// The keyboard layout to practice, if the file sets one.
+    pub(crate) fn layout(&self) -> Option<&KeyboardLayout> {
+        self.layout.as_ref()
+    }
+
+    /// The colours of the theme, with those of `colors` replaced.
+    pub(crate) fn palette(&self) -> Palette {
+        self.palette
+    }
+
+    /// The keys of every action, the default ones unless rebound.
+    pub(crate) fn keymap(&self) -> &Keymap {
+        &self.keymap
+    }
+
+    /// The names of the settings that differ from `started_from`, the
+    /// configuration the running session started from. They apply from the
+    /// next session.
+    pub(crate) fn next_session_changes(&self, started_from: &Config) -> Vec<&'static str> {
+        SESSION_SETTINGS
+            .iter()
+            .filter(|(_, same)| !same(self, started_from))
+            .map(|(name, _)| *name)
+            .collect()
+    }
+}
+
+/// The palette of the built-in or user theme `name`, with `seen` the user
+/// themes based on it so far.
+fn resolve_palette<'a>(
+    name: &'a str,
+    themes: &'a BTreeMap<String, ThemeColors>,
+    seen: &mut Vec<&'a str>,
+) -> Result<Palette> {
+    let Some(colors) = themes.get(name) else {
+        return Palette::builtin(name).with_context(|| {
+            format!(
+                "unknown theme `{name}`, the built-in themes are {}",
+                Palette::NAMES.join(", ")
+            )
+        });
+    };
+    if seen.contains(&name) {
+        bail!("theme `{name}` is based on itself");
+    }
+    seen.push(name);
+    let base = colors.base.as_deref().unwrap_or("dark");
+    Ok(resolve_palette(base, themes, seen)?.with(colors))
+}
+
+/// Which optional panels are shown while typing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Panels {
+    /// The keyboard heatmap
+    pub(crate) keyboard: bool,
+    /// The live session metrics
+    pub(crate) session: bool,
+    /// Problem words, struggle combinations and fastest and slowest words
+    pub(crate) stats: bool,
+}
+
+impl Default for Panels {
+    fn default() -> Self {
+        Panels {
+            keyboard: true,
+            session: true,
+            stats: true,
+        }
+    }
+}
+
+/// The configuration file path, inside the user config directory.
+pub(crate) fn config_path() -> Result<PathBuf> {
+    let config_dir = dirs::config_dir().context("could not determine the user config directory")?;
+    Ok(config_dir.join("dvoratt").join("config.toml"))
+}
+
+/// The configuration, reloaded when its file changes.
+pub(crate) struct ConfigWatcher {
+    path: PathBuf,
+    /// Modification time and size of the file when it was last read
+    stamp: Option<(SystemTime, u64)>,
+    config: Config,
+}
+
+impl ConfigWatcher {
+    /// Load the configuration file at `path` and watch it for changes.
+    pub(crate) fn new(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let stamp = file_stamp(&path);
+        let config = Config::load(&path)?;
+        Ok(ConfigWatcher {
+            path,
+            stamp,
+            config,
+        })
+    }
+
+    /// Watch the configuration file at its default location.
+    pub(crate) fn open_default() -> Result<Self> {
+        Self::new(config_path()?)
+    }
+
+    pub(crate) fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Reload the file if it changed since it was last read, and tell whether
+    /// the configuration changed.
+    ///
+    /// An invalid file keeps the previous configuration and is read again on
+    /// its next change.
+    pub(crate) fn poll(&mut self) -> Result<bool> {
+        let stamp = file_stamp(&self.path);
+        if stamp == self.stamp {
+            return Ok(false);
+        }
+        self.stamp = stamp;
+        let config = Config::load(&self.path)?;
+        let changed = config != self.config;
+        self.config = config;
+        Ok(changed)
+    }
+}
+
+fn file_stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{InputEvent, KeyChord, KeyName};
+    use crate::performance::LearnedSpeed;
+    use ratatui::style::Color;
+    use std::fs::File;
+    use std::time::Duration;
+
+    #[test]
+    fn test_missing_file_is_default() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::load(&dir.path().join("config.toml")).unwrap();
+        assert_eq!(config, Config::default());
+        assert_eq!(config.mastery, MasteryPolicy::default());
+        assert!(config.panels.keyboard);
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let config = Config::parse(
+            r##"
+            level = 3
+            layout = "colemak"
+            seed = 42
+            time = 60
+            input_mode = "stop-on-letter"
+
+            [mastery]
+            speed = "90%"
+            repetitions = 5
+
+            [colors]
+            current_word = "light-blue"
+            mistyped = "#ff8800"
+
+            [panels]
+            keyboard = false
+
+            [keys]
+            submit = "enter"
+            restart = "ctrl+r"
+            quit = ["ctrl+q", "ctrl+c"]
+            "##,
+            Path::new(""),
+        )
+        .unwrap();
+        assert_eq!(config.level, Some(3));
+        assert_eq!(config.layout().unwrap().name, "Colemak");
+        assert_eq!(config.seed, Some(42));
+        assert_eq!(config.time, Some(60));
+        assert_eq!(config.input_mode, Some(InputMode::StopOnLetter));
+        assert_eq!(config.mastery.speed, LearnedSpeed::PercentOfAverage(90.0));
+        assert_eq!(config.mastery.streak, 2);
+        assert_eq!(config.mastery.repetitions, 5);
+        assert_eq!(config.palette().current_word, Color::LightBlue);
+        assert_eq!(config.palette().mistyped, Color::Rgb(0xff, 0x88, 0x00));
+        assert_eq!(config.palette().input, Color::Green);
+        assert!(!config.panels.keyboard);
+        assert!(config.panels.session);
+        let enter = KeyChord::key(KeyName::Enter);
+        assert_eq!(config.keymap().event(enter), Some(InputEvent::Submit));
+        assert_eq!(config.keymap().hint(InputEvent::Quit), "Ctrl+Q");
+    }
+
+    #[test]
+    fn test_user_themes() {
+        let config = Config::parse(
+            r##"
+            theme = "mine"
+
+            [themes.base]
+            base = "light"
+            stats = "black"
+
+            [themes.mine]
+            base = "base"
+            mistyped = "magenta"
+
+            [colors]
+            input = "blue"
+            "##,
+            Path::new(""),
+        )
+        .unwrap();
+        let palette = config.palette();
+        assert_eq!(palette.mistyped, Color::Magenta);
+        assert_eq!(palette.stats, Color::Black);
+        assert_eq!(palette.input, Color::Blue);
+        assert_eq!(palette.current_word, Palette::LIGHT.current_word);
+
+        let config = Config::parse("theme = \"high-contrast\"", Path::new("")).unwrap();
+        assert_eq!(config.palette(), Palette::HIGH_CONTRAST);
+    }
+
+    #[test]
+    fn test_layout_file_relative_to_config() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("mine.toml"),
+            "name = \"Mine\"\nrows = [\"qwertyuiop\", \"asdfghjkl\", \"zxcvbnm\"]\nhome_row = 1\n",
+        )
+        .unwrap();
+        let config = Config::parse("layout = \"mine.toml\"", dir.path()).unwrap();
+        assert_eq!(config.layout().unwrap().name, "Mine");
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let parse = |contents| Config::parse(contents, Path::new(""));
+        assert!(parse("level = 0").is_err());
+        assert!(parse("level = 2\nadaptive = true").is_err());
+        assert!(parse("time = 45").is_err());
+        assert!(parse("input_mode = \"strict\"").is_err());
+        assert!(parse("layout = \"azerty\"").is_err());
+        assert!(parse("[mastery]\nrepetitions = 0").is_err());
+        assert!(parse("[mastery]\nspeed = \"fast\"").is_err());
+        assert!(parse("[colors]\ninput = \"not a colour\"").is_err());
+        assert!(parse("[colors]\nbase = \"light\"").is_err());
+        assert!(parse("theme = \"solarized\"").is_err());
+        assert!(parse("[themes.dark]\ninput = \"blue\"").is_err());
+        assert!(
+            parse("theme = \"a\"\n[themes.a]\nbase = \"b\"\n[themes.b]\nbase = \"a\"").is_err()
+        );
+        assert!(parse("[keys]\npause = \"backspace\"").is_err());
+        assert!(parse("colour = \"red\"").is_err());
+    }
+
+    #[test]
+    fn test_watcher_reloads_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        let mut watcher = ConfigWatcher::new(&path).unwrap();
+        assert!(!watcher.poll().unwrap());
+
+        let write = |contents: &str, age: u64| {
+            fs::write(&path, contents).unwrap();
+            let modified = SystemTime::now() - Duration::from_secs(age);
+            File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(modified)
+                .unwrap();
+        };
+        write("level = 2", 20);
+        assert!(watcher.poll().unwrap());
+        assert_eq!(watcher.config().level, Some(2));
+        assert!(!watcher.poll().unwrap());
+
+        write("level = 0", 10);
+        assert!(watcher.poll().is_err());
+        assert_eq!(watcher.config().level, Some(2));
+        assert!(!watcher.poll().unwrap());
+
+        write("level = 4", 0);
+        assert!(watcher.poll().unwrap());
+        assert_eq!(watcher.config().level, Some(4));
+    }
+
+    #[test]
+    fn test_next_session_changes() {
+        let parse = |contents: &str| Config::parse(contents, Path::new("")).unwrap();
+        let started_from = parse("level = 2\ntime = 60");
+        assert!(
+            parse("level = 2\ntime = 60\ntheme = \"light\"\n[mastery]\nrepetitions = 5")
+                .next_session_changes(&started_from)
+                .is_empty()
+        );
+        assert_eq!(
+            parse("level = 3\nlayout = \"colemak\"\ninput_mode = \"stop-on-letter\"")
+                .next_session_changes(&started_from),
+            ["level", "layout", "time", "input_mode"]
+        );
+    }
+}
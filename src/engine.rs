@@ -0,0 +1,907 @@
+//! Headless typing engine.
+//!
+//! A [`TypingSession`] holds everything a typing drill needs without a
+//! terminal: the words to type, the input, the mistakes, problem words and
+//! the statistics of the session. Keys are fed with the time they were
+//! pressed, so a session driven from recorded events or tests gives the same
+//! results every time. The terminal interface of `dvoratt` is one consumer of
+//! this API.
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use dvoratt::engine::{Key, KeyOutcome, SessionBuilder, WordSource};
+//! use dvoratt::{Finger, KeyboardLayout, MasteryPolicy};
+//!
+//! let words = vec!["hat".to_string()];
+//! let layout = KeyboardLayout::builtin("qwerty").expect("a built-in layout");
+//! let mastery = MasteryPolicy {
+//!     repetitions: 1,
+//!     ..MasteryPolicy::default()
+//! };
+//! let mut session = SessionBuilder::new(WordSource::Words(words))
+//!     .seed(7)
+//!     .layout(layout)
+//!     .mastery(mastery)
+//!     .build()?;
+//! assert_eq!(session.current_word(), "hat");
+//!
+//! let mut at = Duration::ZERO;
+//! for c in "hxt".chars() {
+//!     session.press(Key::Char(c), at);
+//!     at += Duration::from_millis(200);
+//! }
+//! assert_eq!(session.input(), "hxt");
+//! assert_eq!(session.mistakes(), [1]);
+//!
+//! session.press(Key::Backspace, at);
+//! session.press(Key::Backspace, at);
+//! session.press(Key::Char('a'), at);
+//! session.press(Key::Char('t'), at + Duration::from_millis(200));
+//! let outcome = session.press(Key::Char(' '), at + Duration::from_millis(400));
+//! assert!(matches!(outcome, KeyOutcome::Completed { .. }));
+//!
+//! let results = session.results();
+//! assert_eq!(results.words_typed, 1);
+//! assert_eq!(results.corrected_errors, 1);
+//!
+//! // The miss on `a` counts against it, and against the finger typing it.
+//! let a = results.key_stats.iter().find(|stats| stats.key == 'a').unwrap();
+//! assert_eq!((a.counts.hits, a.counts.misses), (1, 1));
+//! let pinky = &results.finger_stats[0];
+//! assert_eq!(pinky.finger, Finger::LeftPinky);
+//! assert_eq!(pinky.counts.error_rate, 0.5);
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::adaptive::AdaptiveLesson;
+use crate::keyboard_layout::{Finger, KeyboardLayout};
+use crate::performance::{KeyStatsEntry, MasteryPolicy, PerformanceTracker};
+use crate::review::Grade;
+use crate::timed_test::TimedTest;
+use crate::word_lists::load_word_lists;
+use crate::word_queue::WordQueue;
+
+/// Where the words of a session come from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum WordSource {
+    /// A built-in lesson, counted from 1
+    Lesson(usize),
+    /// The given words, in a random order
+    Words(Vec<String>),
+    /// Keys of the layout unlocked one at a time, with words from `dictionary`
+    Adaptive { dictionary: Vec<String> },
+}
+
+/// A key fed to a session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Key {
+    /// A typed character, a space submitting the input
+    Char(char),
+    /// Delete the last character of the input
+    Backspace,
+}
+
+/// How a session handles wrong keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "tui", derive(clap::ValueEnum))]
+#[serde(rename_all = "kebab-case")]
+#[non_exhaustive]
+pub enum InputMode {
+    /// Every key is typed, and the word is judged once submitted
+    #[default]
+    Free,
+    /// Wrong keys are rejected and counted, and the input waits for the right one
+    StopOnLetter,
+    /// The word cannot be submitted until it is typed correctly
+    StopOnWord,
+}
+
+/// What a key did to the session.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum KeyOutcome {
+    /// Nothing: the timed test is over, or there was nothing to delete
+    Ignored,
+    /// A character was added to the input
+    Typed { correct: bool },
+    /// The last character of the input was deleted
+    Deleted,
+    /// The input matched the word, typed at `wpm`
+    Completed { word: String, wpm: f32 },
+    /// The input did not match the word, which becomes a problem word
+    Missed { word: String },
+    /// The input mode refused the key, which left the input unchanged
+    Rejected,
+}
+
+/// Builds a [`TypingSession`].
+#[derive(Debug, Clone)]
+pub struct SessionBuilder {
+    source: WordSource,
+    seed: Option<u64>,
+    time_limit: Option<Duration>,
+    input_mode: InputMode,
+    layout: KeyboardLayout,
+    review_words: Vec<String>,
+    mastery: MasteryPolicy,
+}
+
+impl SessionBuilder {
+    /// A session practicing the words of `source` in a random order, without
+    /// time limit, on the Dvorak layout.
+    pub fn new(source: WordSource) -> Self {
+        SessionBuilder {
+            source,
+            seed: None,
+            time_limit: None,
+            input_mode: InputMode::Free,
+            layout: KeyboardLayout::default(),
+            review_words: Vec::new(),
+            mastery: MasteryPolicy::default(),
+        }
+    }
+
+    /// Draw the words in the order given by `seed`.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Make the session a timed test of `limit`, starting on the first key.
+    pub fn time_limit(mut self, limit: Duration) -> Self {
+        self.time_limit = Some(limit);
+        self
+    }
+
+    /// Handle wrong keys as `mode` says, instead of typing them.
+    pub fn input_mode(mut self, mode: InputMode) -> Self {
+        self.input_mode = mode;
+        self
+    }
+
+    /// Practice `layout`, which adaptive lessons unlock the keys of and
+    /// finger statistics are attributed with.
+    pub fn layout(mut self, layout: KeyboardLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Mix words due for review into the session, unless it is adaptive.
+    pub fn review_words(mut self, words: Vec<String>) -> Self {
+        self.review_words = words;
+        self
+    }
+
+    /// Repeat and retire problem words as `policy` says.
+    pub fn mastery(mut self, policy: MasteryPolicy) -> Self {
+        self.mastery = policy;
+        self
+    }
+
+    /// Start the session.
+    ///
+    /// Fails if the lesson does not exist or if there is no word to type.
+    pub fn build(self) -> Result<TypingSession> {
+        let mut word_queue = match self.source {
+            WordSource::Lesson(level) => {
+                let lessons = load_word_lists(&[])?;
+                let Some(lesson) = level.checked_sub(1).and_then(|index| lessons.get(index)) else {
+                    bail!(
+                        "lesson {level} does not exist, available lessons are 1 to {}",
+                        lessons.len()
+                    );
+                };
+                WordQueue::new(lesson.words.clone(), self.seed)
+            }
+            WordSource::Words(words) => {
+                if words.is_empty() {
+                    bail!("there are no words to type");
+                }
+                WordQueue::new(words, self.seed)
+            }
+            WordSource::Adaptive { mut dictionary } => {
+                dictionary.sort_unstable();
+                dictionary.dedup();
+                WordQueue::adaptive(AdaptiveLesson::new(&self.layout, dictionary), self.seed)
+            }
+        };
+        // Review words may use keys an adaptive lesson has not unlocked yet.
+        let review_words = if word_queue.adaptive_lesson().is_some() {
+            Vec::new()
+        } else {
+            self.review_words
+        };
+        word_queue.add_review_words(review_words.clone());
+        word_queue.set_required_repetitions(self.mastery.repetitions);
+        Ok(TypingSession {
+            word_queue,
+            performance: PerformanceTracker::default(),
+            user_input: String::new(),
+            timed_test: self.time_limit.map(TimedTest::new),
+            input_mode: self.input_mode,
+            layout: self.layout,
+            mastery: self.mastery,
+            due_reviews: review_words.into_iter().collect(),
+            review_grades: BTreeMap::new(),
+            origin: Instant::now(),
+        })
+    }
+}
+
+/// A word and the speed it was typed at.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct WordSpeed {
+    pub word: String,
+    pub wpm: f32,
+}
+
+/// A word the user struggled with.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct ProblemWord {
+    pub word: String,
+    /// Average speed of the attempts
+    pub wpm: f32,
+    /// Backspaces used in the last failed attempt
+    pub backspaces: u32,
+    /// Correct attempts in a row since the last failure
+    pub correct_attempts: u8,
+}
+
+/// Hits, misses and latency of a group of keystrokes.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct KeystrokeCounts {
+    pub hits: u32,
+    pub misses: u32,
+    /// Misses over attempts, from 0 to 1
+    pub error_rate: f32,
+    /// Time since the previous keystroke, over the hits
+    pub mean_latency: Option<Duration>,
+    pub p90_latency: Option<Duration>,
+}
+
+impl From<&KeyStatsEntry> for KeystrokeCounts {
+    fn from(entry: &KeyStatsEntry) -> Self {
+        KeystrokeCounts {
+            hits: entry.hits,
+            misses: entry.misses,
+            error_rate: entry.error_rate(),
+            mean_latency: entry.mean_latency(),
+            p90_latency: entry.p90_latency(),
+        }
+    }
+}
+
+/// The keystrokes expecting one key.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct KeyResult {
+    pub key: char,
+    pub counts: KeystrokeCounts,
+}
+
+/// The keystrokes expecting the keys of one finger of the layout.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct FingerResult {
+    pub finger: Finger,
+    pub counts: KeystrokeCounts,
+}
+
+/// The statistics of a session.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct SessionResults {
+    /// Words per minute over the correctly typed words
+    pub average_wpm: f32,
+    /// Words per minute counting every keystroke
+    pub raw_wpm: f32,
+    /// Raw speed minus one word per minute for each uncorrected error
+    pub net_wpm: f32,
+    /// Percentage of correct keystrokes
+    pub accuracy: f32,
+    pub words_typed: u32,
+    pub total_keystrokes: u32,
+    pub correct_keystrokes: u32,
+    /// Mistakes fixed with Backspace
+    pub corrected_errors: u32,
+    /// Mistakes left in submitted words
+    pub uncorrected_errors: u32,
+    /// Time between the first and the last key
+    pub duration: Duration,
+    pub problem_words: Vec<ProblemWord>,
+    pub fastest_words: Vec<WordSpeed>,
+    pub slowest_words: Vec<WordSpeed>,
+    /// Every expected key, in character order
+    pub key_stats: Vec<KeyResult>,
+    /// Every finger of the layout that typed a key, from the left pinky
+    pub finger_stats: Vec<FingerResult>,
+}
+
+/// A typing session, driven by keys with explicit timestamps.
+pub struct TypingSession {
+    /// Queue of words to type, including problem words
+    pub(crate) word_queue: WordQueue,
+    /// Performance tracking and statistics
+    pub(crate) performance: PerformanceTracker,
+    /// Current user input for the word being typed
+    pub(crate) user_input: String,
+    /// The running timed test, if the session is one
+    pub(crate) timed_test: Option<TimedTest>,
+    /// How wrong keys are handled
+    input_mode: InputMode,
+    /// The keyboard layout being practiced
+    pub(crate) layout: KeyboardLayout,
+    /// When problem words are mastered and how often they are repeated
+    mastery: MasteryPolicy,
+    /// Words due for review in this session
+    due_reviews: BTreeSet<String>,
+    /// Worst grade of each reviewed or newly mistyped word
+    review_grades: BTreeMap<String, Grade>,
+    /// The instant the timestamps of [`TypingSession::press`] count from
+    origin: Instant,
+}
+
+impl TypingSession {
+    /// Feed `key`, pressed `at` the given time since the session started.
+    pub fn press(&mut self, key: Key, at: Duration) -> KeyOutcome {
+        self.press_at(key, self.origin + at)
+    }
+
+    /// Advance the timed test, if any, to `at` the given time since the
+    /// session started.
+    pub fn tick(&mut self, at: Duration) {
+        self.tick_at(self.origin + at);
+    }
+
+    /// The word to type.
+    pub fn current_word(&self) -> &str {
+        self.word_queue.current_word()
+    }
+
+    /// The words after the current one.
+    pub fn next_words(&self) -> &[String] {
+        self.word_queue.next_words()
+    }
+
+    /// Whether the current word is a problem word being repeated.
+    pub fn is_problem_word(&self) -> bool {
+        self.word_queue.is_current_word_problem()
+    }
+
+    /// What has been typed of the current word.
+    pub fn input(&self) -> &str {
+        &self.user_input
+    }
+
+    /// Positions of the mistyped characters of the input, in characters.
+    pub fn mistakes(&self) -> &[usize] {
+        self.performance.mistyped_chars()
+    }
+
+    /// The next character to type, or `None` once the word is complete.
+    pub fn next_char(&self) -> Option<char> {
+        self.word_queue
+            .current_word()
+            .chars()
+            .nth(self.user_input.chars().count())
+    }
+
+    /// How the session handles wrong keys.
+    pub fn input_mode(&self) -> InputMode {
+        self.input_mode
+    }
+
+    /// Whether the timed test of the session is over.
+    pub fn is_finished(&self) -> bool {
+        self.timed_test.as_ref().is_some_and(TimedTest::is_finished)
+    }
+
+    /// Time left in the timed test `at` the given time since the session
+    /// started, or `None` if there is no running test.
+    pub fn remaining(&self, at: Duration) -> Option<Duration> {
+        self.timed_test.as_ref()?.remaining(self.origin + at)
+    }
+
+    /// The statistics of the session so far.
+    pub fn results(&self) -> SessionResults {
+        let performance = &self.performance;
+        let stats = performance.keystroke_stats();
+        let word_speeds = |words: &[(String, f32)]| {
+            words
+                .iter()
+                .map(|(word, wpm)| WordSpeed {
+                    word: word.clone(),
+                    wpm: *wpm,
+                })
+                .collect()
+        };
+        SessionResults {
+            average_wpm: performance.average_wpm(),
+            raw_wpm: performance.raw_wpm(),
+            net_wpm: performance.net_wpm(),
+            accuracy: performance.accuracy(),
+            words_typed: stats.words_typed,
+            total_keystrokes: stats.total_keystrokes,
+            correct_keystrokes: stats.correct_keystrokes,
+            corrected_errors: stats.corrected_errors,
+            uncorrected_errors: stats.uncorrected_errors,
+            duration: performance.typing_duration(),
+            problem_words: performance
+                .get_problem_words()
+                .iter()
+                .map(|entry| ProblemWord {
+                    word: entry.word.clone(),
+                    wpm: entry.avg_speed,
+                    backspaces: entry.backspaces,
+                    correct_attempts: entry.correct_attempts,
+                })
+                .collect(),
+            fastest_words: word_speeds(performance.get_fastest_words()),
+            slowest_words: word_speeds(performance.get_slowest_words()),
+            key_stats: performance
+                .key_stats()
+                .iter()
+                .map(|(key, entry)| KeyResult {
+                    key,
+                    counts: entry.into(),
+                })
+                .collect(),
+            finger_stats: performance
+                .key_stats()
+                .by_finger(&self.layout)
+                .iter()
+                .map(|(finger, entry)| FingerResult {
+                    finger: *finger,
+                    counts: entry.into(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Handle `key` as if it was pressed at `now`.
+    pub(crate) fn press_at(&mut self, key: Key, now: Instant) -> KeyOutcome {
+        if let Some(test) = &mut self.timed_test
+            && test.update(now, self.performance.keystroke_stats())
+        {
+            return KeyOutcome::Ignored;
+        }
+
+        let latency = self
+            .performance
+            .last_keypress_time()
+            .map(|last_time| now.duration_since(last_time));
+
+        self.performance.set_last_keypress_time(now);
+
+        match key {
+            Key::Char(c) => {
+                self.performance.start_word_if_needed(now);
+                if let Some(test) = &mut self.timed_test {
+                    test.start_if_needed(now, self.performance.keystroke_stats());
+                }
+                if self.refuses(c) {
+                    return self.reject(c, latency, now);
+                }
+                if c == ' ' {
+                    let current_word = self.word_queue.current_word();
+                    self.performance
+                        .record_keystroke(self.user_input == current_word);
+                    self.performance
+                        .record_submitted_word(&self.user_input, current_word);
+                    self.on_word_completed(now)
+                } else {
+                    let position = self.user_input.chars().count();
+                    let expected_char = self.next_char();
+                    if expected_char.is_some_and(|expected| expected != c) {
+                        self.performance.record_mistype(position);
+                    }
+                    self.performance.record_keystroke(expected_char == Some(c));
+                    self.performance
+                        .update_struggle_combinations(c, expected_char == Some(c), now);
+                    if let Some(expected) = expected_char {
+                        self.performance
+                            .record_key_press(expected, c == expected, latency);
+                    }
+                    self.user_input.push(c);
+                    KeyOutcome::Typed {
+                        correct: expected_char == Some(c),
+                    }
+                }
+            }
+            Key::Backspace if !self.user_input.is_empty() => {
+                let removed = self.user_input.pop();
+                let expected = self.next_char();
+                if removed != expected {
+                    self.performance.record_corrected_error();
+                }
+                self.performance
+                    .undo_mistype_at(self.user_input.chars().count());
+                self.performance.record_backspace();
+                if self.mastery.backspace_fails {
+                    self.add_problem_word(now);
+                }
+                KeyOutcome::Deleted
+            }
+            Key::Backspace => KeyOutcome::Ignored,
+        }
+    }
+
+    /// Whether the input mode refuses to type `c`.
+    fn refuses(&self, c: char) -> bool {
+        let complete = self.user_input == self.word_queue.current_word();
+        match self.input_mode {
+            InputMode::Free => false,
+            InputMode::StopOnLetter if c == ' ' => !complete,
+            InputMode::StopOnLetter => self.next_char() != Some(c),
+            InputMode::StopOnWord => c == ' ' && !complete,
+        }
+    }
+
+    /// Refuse `c`. A wrong letter still counts as a corrected error of the
+    /// key expected instead, and makes the word a problem word; a refused
+    /// space only waits for the word to be fixed.
+    fn reject(&mut self, c: char, latency: Option<Duration>, now: Instant) -> KeyOutcome {
+        if c != ' ' {
+            self.performance.record_keystroke(false);
+            self.performance.record_corrected_error();
+            self.performance.update_struggle_combinations(c, false, now);
+            if let Some(expected) = self.next_char() {
+                self.performance.record_key_press(expected, false, latency);
+            }
+            self.add_problem_word(now);
+        }
+        KeyOutcome::Rejected
+    }
+
+    fn on_word_completed(&mut self, now: Instant) -> KeyOutcome {
+        let word = self.word_queue.current_word().to_string();
+        let outcome = if self.user_input == word {
+            let speed = self.calculate_word_speed(now);
+            self.performance.update_recent_word_speeds(speed);
+            self.performance.record_speed_timeline(speed, now);
+            self.performance
+                .update_fastest_slowest_words(&self.user_input, speed);
+            self.performance
+                .record_word_completed(word.chars().count() as u32, now);
+            if self.due_reviews.contains(&self.user_input) {
+                let grade = if self
+                    .mastery
+                    .is_fast_enough(speed, self.performance.average_wpm())
+                {
+                    Grade::Good
+                } else {
+                    Grade::Hard
+                };
+                self.grade_review(self.user_input.clone(), grade);
+            }
+
+            if self.word_queue.is_current_word_problem() {
+                self.word_queue.update_problem_word_correct_attempt();
+                if self.word_queue.get_current_problem_word_repetitions()
+                    >= self.mastery.repetitions
+                {
+                    self.performance
+                        .update_problem_word_correct_attempts(&word, speed);
+                }
+            } else if self.mastery.backspace_fails && self.performance.backspace_used() {
+                self.add_problem_word(now);
+            } else {
+                self.performance
+                    .update_problem_word_correct_attempts(&word, speed);
+            }
+
+            self.performance.remove_learned_words(&self.mastery);
+            self.word_queue
+                .update_adaptive_lesson(self.performance.key_stats());
+            self.word_queue.next_word();
+            KeyOutcome::Completed { word, wpm: speed }
+        } else {
+            self.add_problem_word(now);
+            KeyOutcome::Missed { word }
+        };
+        self.user_input.clear();
+        self.performance.reset_word_state();
+        outcome
+    }
+
+    fn add_problem_word(&mut self, now: Instant) {
+        let speed = self.calculate_word_speed(now);
+        let current_word = self.word_queue.current_word().to_string();
+        self.performance
+            .add_problem_word(current_word.clone(), speed);
+        self.word_queue.add_problem_word(current_word.clone());
+        self.grade_review(current_word, Grade::Again);
+    }
+
+    /// Keep the worst grade a word gets during the session, so that typing it
+    /// again after a mistake does not count as a successful review.
+    fn grade_review(&mut self, word: String, grade: Grade) {
+        self.review_grades
+            .entry(word)
+            .and_modify(|worst| *worst = (*worst).min(grade))
+            .or_insert(grade);
+    }
+
+    /// The review outcome of each word graded during the session.
+    pub(crate) fn review_grades(&self) -> &BTreeMap<String, Grade> {
+        &self.review_grades
+    }
+
+    /// Speed of the current word in WPM, 0 for an empty word or one typed in
+    /// no time.
+    fn calculate_word_speed(&self, now: Instant) -> f32 {
+        let Some(start_time) = self.performance.word_start_time() else {
+            return 0.0;
+        };
+        let elapsed = now.saturating_duration_since(start_time);
+        let chars = self.word_queue.current_word().chars().count();
+        if chars == 0 || elapsed.is_zero() {
+            return 0.0;
+        }
+        (chars as f32 / 5.0) / (elapsed.as_secs_f32() / 60.0)
+    }
+
+    pub(crate) fn mastery_policy(&self) -> MasteryPolicy {
+        self.mastery
+    }
+
+    pub(crate) fn set_mastery_policy(&mut self, policy: MasteryPolicy) {
+        self.mastery = policy;
+        self.word_queue.set_required_repetitions(policy.repetitions);
+    }
+
+    /// Advance time-dependent state, such as the timed test, to `now`.
+    pub(crate) fn tick_at(&mut self, now: Instant) {
+        if let Some(test) = &mut self.timed_test {
+            test.update(now, self.performance.keystroke_stats());
+        }
+    }
+
+    /// Start a new timed test of the same length, on the next key, and clear
+    /// the input.
+    pub(crate) fn restart(&mut self) {
+        if let Some(test) = &mut self.timed_test {
+            test.reset();
+        }
+        self.performance.reset_word_state();
+        self.user_input.clear();
+    }
+
+    /// Practice `words` instead, unless the session is adaptive, and restart
+    /// the timed test. Returns whether the words changed.
+    pub(crate) fn change_words(&mut self, words: Vec<String>) -> bool {
+        if self.word_queue.adaptive_lesson().is_some() {
+            return false;
+        }
+        self.word_queue.change_word_list(words);
+        self.restart();
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(list: &[&str]) -> WordSource {
+        WordSource::Words(list.iter().map(|word| word.to_string()).collect())
+    }
+
+    /// Type `text` one key every 200 ms from `at`.
+    fn type_text(session: &mut TypingSession, at: &mut Duration, text: &str) -> Vec<KeyOutcome> {
+        text.chars()
+            .map(|c| {
+                *at += Duration::from_millis(200);
+                session.press(Key::Char(c), *at)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_build_sources() {
+        assert!(SessionBuilder::new(WordSource::Lesson(1)).build().is_ok());
+        assert!(SessionBuilder::new(WordSource::Lesson(0)).build().is_err());
+        assert!(SessionBuilder::new(WordSource::Lesson(99)).build().is_err());
+        assert!(SessionBuilder::new(words(&[])).build().is_err());
+
+        let adaptive = SessionBuilder::new(WordSource::Adaptive {
+            dictionary: vec!["hut".to_string(), "tide".to_string()],
+        })
+        .review_words(vec!["zebra".to_string()])
+        .build()
+        .unwrap();
+        assert_ne!(adaptive.current_word(), "zebra");
+    }
+
+    #[test]
+    fn test_same_seed_same_words() {
+        let build = || {
+            SessionBuilder::new(WordSource::Lesson(2))
+                .seed(42)
+                .build()
+                .unwrap()
+        };
+        let (a, b) = (build(), build());
+        assert_eq!(a.current_word(), b.current_word());
+        assert_eq!(a.next_words(), b.next_words());
+    }
+
+    #[test]
+    fn test_completed_word_speed() {
+        let mut session = SessionBuilder::new(words(&["hat"])).build().unwrap();
+        let mut at = Duration::ZERO;
+        let outcomes = type_text(&mut session, &mut at, "hat ");
+        assert_eq!(outcomes[0], KeyOutcome::Typed { correct: true });
+        // Three characters from the first key to the space, in 600 ms.
+        assert_eq!(
+            outcomes[3],
+            KeyOutcome::Completed {
+                word: "hat".to_string(),
+                wpm: 60.0
+            }
+        );
+        let results = session.results();
+        assert_eq!(results.average_wpm, 60.0);
+        assert_eq!(results.accuracy, 100.0);
+        assert_eq!(results.duration, Duration::from_millis(600));
+        assert_eq!(results.fastest_words[0].word, "hat");
+    }
+
+    #[test]
+    fn test_missed_word_becomes_problem_word() {
+        let mut session = SessionBuilder::new(words(&["hat", "cat"])).build().unwrap();
+        let word = session.current_word().to_string();
+        let mut at = Duration::ZERO;
+        let outcomes = type_text(&mut session, &mut at, "xyz ");
+        assert_eq!(outcomes[0], KeyOutcome::Typed { correct: false });
+        assert_eq!(outcomes[3], KeyOutcome::Missed { word: word.clone() });
+        assert_eq!(session.current_word(), word);
+        assert!(session.is_problem_word());
+        assert_eq!(session.input(), "");
+        assert_eq!(session.results().problem_words[0].word, word);
+        assert_eq!(session.results().uncorrected_errors, 3);
+    }
+
+    #[test]
+    fn test_one_word_list() {
+        let mut session = SessionBuilder::new(words(&["hat", "cat"])).build().unwrap();
+        assert!(session.change_words(vec!["a".to_string()]));
+        let mut at = Duration::ZERO;
+        type_text(&mut session, &mut at, "a a ");
+        assert_eq!(session.current_word(), "a");
+        // A word typed in no time has no speed rather than an infinite one.
+        session.press(Key::Char('a'), at);
+        assert_eq!(
+            session.press(Key::Char(' '), at),
+            KeyOutcome::Completed {
+                word: "a".to_string(),
+                wpm: 0.0
+            }
+        );
+        let results = session.results();
+        assert!(results.average_wpm.is_finite());
+        assert!(
+            results
+                .fastest_words
+                .iter()
+                .all(|word| word.wpm.is_finite())
+        );
+    }
+
+    #[test]
+    fn test_input_and_mistakes() {
+        let mut session = SessionBuilder::new(words(&["hat"])).build().unwrap();
+        let mut at = Duration::ZERO;
+        type_text(&mut session, &mut at, "hu");
+        assert_eq!(session.input(), "hu");
+        assert_eq!(session.mistakes(), [1]);
+        assert_eq!(session.next_char(), Some('t'));
+        assert_eq!(session.press(Key::Backspace, at), KeyOutcome::Deleted);
+        assert!(session.mistakes().is_empty());
+        assert_eq!(session.next_char(), Some('a'));
+        session.press(Key::Backspace, at);
+        assert_eq!(session.press(Key::Backspace, at), KeyOutcome::Ignored);
+    }
+
+    #[test]
+    fn test_non_ascii_word() {
+        let mut session = SessionBuilder::new(words(&["crème"])).build().unwrap();
+        let mut at = Duration::ZERO;
+        let outcomes = type_text(&mut session, &mut at, "crèmx");
+        assert_eq!(outcomes[3], KeyOutcome::Typed { correct: true });
+        assert_eq!(session.mistakes(), [4]);
+        assert_eq!(session.next_char(), None);
+        session.press(Key::Backspace, at);
+        assert!(session.mistakes().is_empty());
+        assert_eq!(session.next_char(), Some('e'));
+
+        type_text(&mut session, &mut at, "e ");
+        let results = session.results();
+        assert_eq!(results.correct_keystrokes, 6);
+        assert_eq!(results.corrected_errors, 1);
+        // Five characters from the first key to the space, in 1.2 s.
+        assert!((results.average_wpm - 50.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_stop_on_letter() {
+        let mut session = SessionBuilder::new(words(&["hat"]))
+            .input_mode(InputMode::StopOnLetter)
+            .build()
+            .unwrap();
+        let mut at = Duration::ZERO;
+        // An early space is only refused.
+        let outcomes = type_text(&mut session, &mut at, "h ");
+        assert_eq!(outcomes[1], KeyOutcome::Rejected);
+        assert_eq!(session.results().total_keystrokes, 1);
+        assert!(session.results().problem_words.is_empty());
+
+        let outcomes = type_text(&mut session, &mut at, "x");
+        assert_eq!(outcomes[0], KeyOutcome::Rejected);
+        assert_eq!(session.input(), "h");
+        assert!(session.mistakes().is_empty());
+
+        let outcomes = type_text(&mut session, &mut at, "at ");
+        assert!(matches!(outcomes[2], KeyOutcome::Completed { .. }));
+        let results = session.results();
+        assert_eq!(results.total_keystrokes, 5);
+        assert_eq!(results.correct_keystrokes, 4);
+        assert_eq!(results.corrected_errors, 1);
+        assert_eq!(results.uncorrected_errors, 0);
+        assert_eq!(results.problem_words[0].word, "hat");
+    }
+
+    #[test]
+    fn test_stop_on_word() {
+        let mut session = SessionBuilder::new(words(&["hat"]))
+            .input_mode(InputMode::StopOnWord)
+            .build()
+            .unwrap();
+        let mut at = Duration::ZERO;
+        let outcomes = type_text(&mut session, &mut at, "hxt ");
+        assert_eq!(outcomes[1], KeyOutcome::Typed { correct: false });
+        assert_eq!(outcomes[3], KeyOutcome::Rejected);
+        assert_eq!(session.input(), "hxt");
+
+        session.press(Key::Backspace, at);
+        session.press(Key::Backspace, at);
+        let outcomes = type_text(&mut session, &mut at, "at ");
+        assert!(matches!(outcomes[2], KeyOutcome::Completed { .. }));
+        let results = session.results();
+        assert_eq!(results.words_typed, 1);
+        assert_eq!(results.corrected_errors, 1);
+        assert_eq!(results.uncorrected_errors, 0);
+    }
+
+    #[test]
+    fn test_timed_session() {
+        let mut session = SessionBuilder::new(words(&["hat"]))
+            .time_limit(Duration::from_secs(15))
+            .build()
+            .unwrap();
+        assert_eq!(session.remaining(Duration::ZERO), None);
+        session.press(Key::Char('h'), Duration::from_secs(1));
+        assert_eq!(
+            session.remaining(Duration::from_secs(6)),
+            Some(Duration::from_secs(10))
+        );
+        session.tick(Duration::from_secs(16));
+        assert!(session.is_finished());
+        assert_eq!(
+            session.press(Key::Char('a'), Duration::from_secs(17)),
+            KeyOutcome::Ignored
+        );
+    }
+}
@@ -0,0 +1,315 @@
+//! Races against the ghost of a recorded session.
+//!
+//! The ghost replays the word completions of a session log: each word it
+//! typed correctly counts at the time it was submitted, measured from its
+//! first keystroke. The player's own clock starts on their first keystroke,
+//! so both sides are compared over the same elapsed time. Sessions started
+//! from the ghost's options present the same seeded word sequence, and words
+//! are matched by their position in it, so that a word one side missed and
+//! repeated as a problem word does not count against a different word of the
+//! other side.
+
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, bail};
+
+use crate::input::InputEvent;
+use crate::session_log::LogEvent;
+
+/// A correctly typed word, at its offset from the first keystroke.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct WordMark {
+    /// Position of the word among the new words of the session
+    index: usize,
+    at: Duration,
+    chars: usize,
+}
+
+/// Add `mark` to `marks` unless its word was already typed, as happens when a
+/// problem word is repeated.
+fn push_mark(marks: &mut Vec<WordMark>, mark: WordMark) {
+    if marks.last().is_none_or(|last| mark.index > last.index) {
+        marks.push(mark);
+    }
+}
+
+/// Words per minute over `marks`, counting five characters per word.
+fn wpm(marks: &[WordMark]) -> f32 {
+    let Some(last) = marks.last() else {
+        return 0.0;
+    };
+    let minutes = last.at.as_secs_f32() / 60.0;
+    if minutes == 0.0 {
+        return 0.0;
+    }
+    let chars: usize = marks.iter().map(|mark| mark.chars).sum();
+    (chars as f32 / 5.0) / minutes
+}
+
+/// A race of the player against a recorded session.
+#[derive(Debug, Clone)]
+pub(crate) struct GhostRace {
+    ghost: Vec<WordMark>,
+    player: Vec<WordMark>,
+    started_at: Option<Instant>,
+}
+
+impl GhostRace {
+    /// Build the ghost of a recorded session from its events.
+    ///
+    /// Fails if the session has no correctly typed word to race against.
+    pub(crate) fn from_events(events: &[LogEvent]) -> Result<Self> {
+        let mut ghost = Vec::new();
+        let mut first_key = None;
+        let mut input = "";
+        for event in events {
+            if let InputEvent::Type(_) | InputEvent::Submit = event.event {
+                let start = *first_key.get_or_insert(event.at());
+                if event.event == InputEvent::Submit && input == event.expected {
+                    let mark = WordMark {
+                        index: event.word,
+                        at: event.at() - start,
+                        chars: event.expected.chars().count(),
+                    };
+                    push_mark(&mut ghost, mark);
+                }
+            }
+            input = &event.input;
+        }
+        if ghost.is_empty() {
+            bail!("the recorded session has no completed word to race against");
+        }
+        Ok(GhostRace {
+            ghost,
+            player: Vec::new(),
+            started_at: None,
+        })
+    }
+
+    /// Start the race clock, if it is not running yet.
+    pub(crate) fn start_if_needed(&mut self, now: Instant) {
+        self.started_at.get_or_insert(now);
+    }
+
+    /// Record a word the player typed correctly at position `index` of the
+    /// word sequence. Repeated words and words after the finish line are not
+    /// counted.
+    pub(crate) fn record_word(&mut self, index: usize, chars: usize, now: Instant) {
+        let Some(started_at) = self.started_at else {
+            return;
+        };
+        if !self.is_finished() {
+            let mark = WordMark {
+                index,
+                at: now.saturating_duration_since(started_at),
+                chars,
+            };
+            push_mark(&mut self.player, mark);
+        }
+    }
+
+    /// Start over, on the next keystroke.
+    pub(crate) fn restart(&mut self) {
+        self.player.clear();
+        self.started_at = None;
+    }
+
+    /// Words in the race, as many as the ghost typed.
+    pub(crate) fn total_words(&self) -> usize {
+        self.ghost.len()
+    }
+
+    /// Words of the race the player is past.
+    pub(crate) fn player_words(&self) -> usize {
+        self.player.last().map_or(0, |last| {
+            self.ghost.partition_point(|mark| mark.index <= last.index)
+        })
+    }
+
+    /// Words the ghost had typed at `now`.
+    pub(crate) fn ghost_words(&self, now: Instant) -> usize {
+        match self.started_at {
+            Some(started_at) => {
+                let elapsed = now.saturating_duration_since(started_at);
+                self.ghost.partition_point(|mark| mark.at <= elapsed)
+            }
+            None => 0,
+        }
+    }
+
+    /// Whether the player is past the last word of the race.
+    pub(crate) fn is_finished(&self) -> bool {
+        self.player_words() >= self.ghost.len()
+    }
+
+    /// Comparison over the words both sides typed correctly so far, or `None`
+    /// before the first one.
+    pub(crate) fn result(&self) -> Option<RaceResult> {
+        let (player, ghost): (Vec<WordMark>, Vec<WordMark>) = self
+            .player
+            .iter()
+            .filter_map(|mark| {
+                let position = self
+                    .ghost
+                    .binary_search_by_key(&mark.index, |ghost| ghost.index)
+                    .ok()?;
+                Some((*mark, self.ghost[position]))
+            })
+            .unzip();
+        let (last_player, last_ghost) = (player.last()?, ghost.last()?);
+        Some(RaceResult {
+            words: player.len(),
+            player_wpm: wpm(&player),
+            ghost_wpm: wpm(&ghost),
+            player_time: last_player.at,
+            ghost_time: last_ghost.at,
+        })
+    }
+}
+
+/// How the player did against the ghost over the same words.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct RaceResult {
+    pub(crate) words: usize,
+    pub(crate) player_wpm: f32,
+    pub(crate) ghost_wpm: f32,
+    pub(crate) player_time: Duration,
+    pub(crate) ghost_time: Duration,
+}
+
+impl RaceResult {
+    /// Whether the player typed the words no slower than the ghost.
+    pub(crate) fn won(&self) -> bool {
+        self.player_time <= self.ghost_time
+    }
+
+    /// Player speed minus ghost speed, in WPM.
+    pub(crate) fn wpm_delta(&self) -> f32 {
+        self.player_wpm - self.ghost_wpm
+    }
+
+    /// Seconds the player was ahead of the ghost, negative when behind.
+    pub(crate) fn time_delta(&self) -> f32 {
+        self.ghost_time.as_secs_f32() - self.player_time.as_secs_f32()
+    }
+
+    /// A one-line summary, such as `won by 1.2s (+4.5 WPM)`.
+    pub(crate) fn summary(&self) -> String {
+        let outcome = if self.won() { "won by" } else { "lost by" };
+        format!(
+            "{outcome} {:.1}s ({:+.1} WPM)",
+            self.time_delta().abs(),
+            self.wpm_delta()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(at_ms: u64, key: char, (word, expected): (usize, &str), input: &str) -> LogEvent {
+        LogEvent {
+            at_us: at_ms * 1000,
+            event: InputEvent::typed(key),
+            expected: expected.to_string(),
+            word,
+            input: input.to_string(),
+            mastery: None,
+        }
+    }
+
+    /// A ghost typing "the" right, "cat" wrong, then "dog" right.
+    fn ghost_events() -> Vec<LogEvent> {
+        let (the, cat, dog) = ((0, "the"), (1, "cat"), (2, "dog"));
+        vec![
+            event(1000, 't', the, "t"),
+            event(1200, 'h', the, "th"),
+            event(1400, 'e', the, "the"),
+            event(1600, ' ', the, ""),
+            event(1800, 'x', cat, "x"),
+            event(2000, ' ', cat, ""),
+            event(2200, 'd', dog, "d"),
+            event(2400, 'o', dog, "do"),
+            event(2600, 'g', dog, "dog"),
+            event(3400, ' ', dog, ""),
+        ]
+    }
+
+    #[test]
+    fn test_ghost_from_events() {
+        let race = GhostRace::from_events(&ghost_events()).unwrap();
+        assert_eq!(race.total_words(), 2);
+        assert_eq!(race.ghost[0].at, Duration::from_millis(600));
+        assert_eq!(race.ghost[1].at, Duration::from_millis(2400));
+        assert!(GhostRace::from_events(&ghost_events()[..5]).is_ok());
+        assert!(GhostRace::from_events(&ghost_events()[..3]).is_err());
+    }
+
+    #[test]
+    fn test_ghost_progress() {
+        let mut race = GhostRace::from_events(&ghost_events()).unwrap();
+        let start = Instant::now();
+        assert_eq!(race.ghost_words(start + Duration::from_secs(10)), 0);
+
+        race.start_if_needed(start);
+        race.start_if_needed(start + Duration::from_secs(1));
+        assert_eq!(race.ghost_words(start), 0);
+        assert_eq!(race.ghost_words(start + Duration::from_millis(600)), 1);
+        assert_eq!(race.ghost_words(start + Duration::from_secs(3)), 2);
+    }
+
+    #[test]
+    fn test_race_result() {
+        let mut race = GhostRace::from_events(&ghost_events()).unwrap();
+        let start = Instant::now();
+        assert_eq!(race.result(), None);
+
+        race.start_if_needed(start);
+        race.record_word(0, 3, start + Duration::from_millis(500));
+        let result = race.result().unwrap();
+        assert_eq!(result.words, 1);
+        assert!(result.won());
+        assert!((result.time_delta() - 0.1).abs() < 1e-4);
+        assert!((result.wpm_delta() - 12.0).abs() < 1e-3);
+
+        race.record_word(2, 3, start + Duration::from_millis(3000));
+        assert!(race.is_finished());
+        race.record_word(3, 3, start + Duration::from_millis(3100));
+        assert_eq!(race.player_words(), 2);
+        let result = race.result().unwrap();
+        assert!(!result.won());
+        assert_eq!(result.summary(), "lost by 0.6s (-6.0 WPM)");
+
+        race.restart();
+        assert_eq!(race.player_words(), 0);
+        assert_eq!(race.ghost_words(start + Duration::from_secs(5)), 0);
+    }
+
+    #[test]
+    fn test_race_compares_the_same_words() {
+        let mut race = GhostRace::from_events(&ghost_events()).unwrap();
+        let start = Instant::now();
+        race.start_if_needed(start);
+
+        // The player misses "the", which the ghost typed right, and repeats it
+        // as a problem word: only its first correct repetition counts.
+        for at_ms in [900, 1300, 1700] {
+            race.record_word(0, 3, start + Duration::from_millis(at_ms));
+        }
+        assert_eq!(race.player_words(), 1);
+        assert!(!race.is_finished());
+
+        // "cat", which the ghost missed, is not compared.
+        race.record_word(1, 3, start + Duration::from_millis(2000));
+        assert_eq!(race.player_words(), 1);
+        race.record_word(2, 3, start + Duration::from_millis(2300));
+        assert!(race.is_finished());
+
+        let result = race.result().unwrap();
+        assert_eq!(result.words, 2);
+        assert_eq!(result.player_time, Duration::from_millis(2300));
+        assert_eq!(result.ghost_time, Duration::from_millis(2400));
+        assert!(result.won());
+    }
+}
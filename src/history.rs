@@ -0,0 +1,352 @@
+//! Persistent history of finished practice sessions.
+//!
+//! Sessions are stored as a single versioned JSON document in the user data
+//! directory (`$XDG_DATA_HOME/dvoratt/history.json` on Linux). Documents written
+//! by older releases are migrated one schema version at a time when loaded, and
+//! saved back in the current schema on the next write. Documents of a newer
+//! release are rejected rather than misread.
+
+use anyhow::{Context, Result, bail};
+use jiff::Timestamp;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Schema version written by this release.
+pub(crate) const SCHEMA_VERSION: u32 = 1;
+
+/// Upgrades a document from schema version `n` to `n + 1`, stored at index `n - 1`.
+type Migration = fn(&mut Value) -> Result<()>;
+
+const MIGRATIONS: &[Migration] = &[];
+
+/// Summary of one finished practice session.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct SessionRecord {
+    pub(crate) timestamp: Timestamp,
+    /// Word list level, counted from 1
+    pub(crate) level: usize,
+    pub(crate) level_name: String,
+    /// Whether the words came from an adaptive lesson rather than a word list
+    pub(crate) adaptive: bool,
+    pub(crate) average_wpm: f32,
+    pub(crate) keystrokes: KeystrokeRecord,
+    pub(crate) problem_words: Vec<ProblemWordRecord>,
+    pub(crate) struggle_combinations: Vec<CombinationRecord>,
+    pub(crate) fastest_words: Vec<WordSpeedRecord>,
+    pub(crate) slowest_words: Vec<WordSpeedRecord>,
+    /// Keystroke log of the session, if one was written
+    pub(crate) log_file: Option<PathBuf>,
+    /// Seed of the word order, if the session was seeded
+    pub(crate) seed: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct KeystrokeRecord {
+    pub(crate) accuracy: f32,
+    pub(crate) raw_wpm: f32,
+    pub(crate) net_wpm: f32,
+    pub(crate) words_typed: u32,
+    pub(crate) total_keystrokes: u32,
+    pub(crate) correct_keystrokes: u32,
+    pub(crate) corrected_errors: u32,
+    pub(crate) uncorrected_errors: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct ProblemWordRecord {
+    pub(crate) word: String,
+    pub(crate) speed: f32,
+    pub(crate) backspaces: u32,
+    pub(crate) correct_attempts: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct CombinationRecord {
+    pub(crate) combination: String,
+    pub(crate) speed: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct WordSpeedRecord {
+    pub(crate) word: String,
+    pub(crate) speed: f32,
+}
+
+/// All recorded sessions, oldest first.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct History {
+    pub(crate) sessions: Vec<SessionRecord>,
+}
+
+impl History {
+    /// The keystroke log of the fastest session whose log still exists.
+    ///
+    /// Adaptive sessions are left out: their words follow the typing of the
+    /// player, so another player would not get the same ones.
+    pub(crate) fn best_log_file(&self) -> Option<&Path> {
+        self.sessions
+            .iter()
+            .filter(|session| !session.adaptive)
+            .filter_map(|session| Some((session, session.log_file.as_deref()?)))
+            .filter(|(_, path)| path.is_file())
+            .max_by(|(a, _), (b, _)| a.average_wpm.total_cmp(&b.average_wpm))
+            .map(|(_, path)| path)
+    }
+}
+
+/// On-disk representation of the history, tagged with its schema version.
+#[derive(Serialize)]
+struct Document<'a> {
+    version: u32,
+    #[serde(flatten)]
+    history: &'a History,
+}
+
+/// Reads and writes the session history file.
+pub(crate) struct HistoryStore {
+    path: PathBuf,
+}
+
+impl HistoryStore {
+    pub(crate) fn new(path: impl Into<PathBuf>) -> Self {
+        HistoryStore { path: path.into() }
+    }
+
+    /// Open the store at its default location in the user data directory.
+    pub(crate) fn open_default() -> Result<Self> {
+        let data_dir = dirs::data_dir().context("could not determine the user data directory")?;
+        Ok(Self::new(data_dir.join("dvoratt").join("history.json")))
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Load the history, migrating it to the current schema if needed.
+    ///
+    /// A missing file is an empty history.
+    pub(crate) fn load(&self) -> Result<History> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(History::default());
+            }
+            Err(err) => {
+                return Err(err).with_context(|| format!("failed to read {}", self.path.display()));
+            }
+        };
+        let document: Value = serde_json::from_str(&contents)
+            .with_context(|| format!("{} is not valid JSON", self.path.display()))?;
+        let document = migrate(document, MIGRATIONS)
+            .with_context(|| format!("failed to migrate {}", self.path.display()))?;
+        serde_json::from_value(document)
+            .with_context(|| format!("{} is not a valid history file", self.path.display()))
+    }
+
+    /// Append a finished session to the history.
+    pub(crate) fn append(&self, record: SessionRecord) -> Result<()> {
+        let mut history = self.load()?;
+        history.sessions.push(record);
+        self.save(&history)
+    }
+
+    /// Write the whole history, replacing the file atomically.
+    fn save(&self, history: &History) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create {}", parent.display()))?;
+        }
+        let document = Document {
+            version: SCHEMA_VERSION,
+            history,
+        };
+        let contents = serde_json::to_string_pretty(&document)?;
+        let tmp_path = self.path.with_extension("json.tmp");
+        fs::write(&tmp_path, contents)
+            .with_context(|| format!("failed to write {}", tmp_path.display()))?;
+        fs::rename(&tmp_path, &self.path)
+            .with_context(|| format!("failed to replace {}", self.path.display()))
+    }
+}
+
+/// Bring a raw history document up to the version after the last of
+/// `migrations`, which is [`SCHEMA_VERSION`] for [`MIGRATIONS`].
+fn migrate(mut document: Value, migrations: &[Migration]) -> Result<Value> {
+    let current = migrations.len() as u64 + 1;
+    let version = document
+        .get("version")
+        .and_then(Value::as_u64)
+        .context("missing schema version")?;
+    if version == 0 || version > current {
+        bail!("unsupported schema version {version}, this release reads versions 1 to {current}");
+    }
+    for migration in &migrations[(version - 1) as usize..] {
+        migration(&mut document)?;
+    }
+    document["version"] = current.into();
+    Ok(document)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(level: usize, average_wpm: f32) -> SessionRecord {
+        SessionRecord {
+            timestamp: Timestamp::from_second(1_700_000_000).unwrap(),
+            level,
+            level_name: "Home Row - 10 keys".to_string(),
+            adaptive: false,
+            average_wpm,
+            keystrokes: KeystrokeRecord {
+                accuracy: 95.0,
+                raw_wpm: average_wpm + 5.0,
+                net_wpm: average_wpm,
+                words_typed: 42,
+                total_keystrokes: 200,
+                correct_keystrokes: 190,
+                corrected_errors: 8,
+                uncorrected_errors: 2,
+            },
+            problem_words: vec![ProblemWordRecord {
+                word: "aoeu".to_string(),
+                speed: 12.5,
+                backspaces: 2,
+                correct_attempts: 1,
+            }],
+            struggle_combinations: vec![CombinationRecord {
+                combination: "ht".to_string(),
+                speed: 20.0,
+            }],
+            fastest_words: vec![WordSpeedRecord {
+                word: "the".to_string(),
+                speed: 80.0,
+            }],
+            slowest_words: vec![WordSpeedRecord {
+                word: "queue".to_string(),
+                speed: 10.0,
+            }],
+            log_file: Some(PathBuf::from("sessions/20231114T221320.000000Z.jsonl")),
+            seed: Some(42),
+        }
+    }
+
+    #[test]
+    fn test_load_missing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = HistoryStore::new(dir.path().join("history.json"));
+        assert_eq!(store.load().unwrap(), History::default());
+    }
+
+    #[test]
+    fn test_append_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = HistoryStore::new(dir.path().join("nested").join("history.json"));
+        store.append(record(2, 40.0)).unwrap();
+        store.append(record(3, 35.0)).unwrap();
+
+        let history = store.load().unwrap();
+        assert_eq!(history.sessions, vec![record(2, 40.0), record(3, 35.0)]);
+    }
+
+    #[test]
+    fn test_saved_document_is_versioned() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = HistoryStore::new(dir.path().join("history.json"));
+        store.append(record(1, 20.0)).unwrap();
+
+        let raw: Value = serde_json::from_str(&fs::read_to_string(store.path()).unwrap()).unwrap();
+        assert_eq!(raw["version"], SCHEMA_VERSION);
+        assert_eq!(raw["sessions"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_best_log_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let with_log = |average_wpm, name: &str| {
+            let path = dir.path().join(name);
+            fs::write(&path, "").unwrap();
+            SessionRecord {
+                log_file: Some(path),
+                ..record(1, average_wpm)
+            }
+        };
+        let mut history = History {
+            sessions: vec![
+                with_log(30.0, "slow.jsonl"),
+                SessionRecord {
+                    log_file: None,
+                    ..record(1, 90.0)
+                },
+                SessionRecord {
+                    log_file: Some(dir.path().join("deleted.jsonl")),
+                    ..record(1, 80.0)
+                },
+                with_log(45.0, "fast.jsonl"),
+                SessionRecord {
+                    adaptive: true,
+                    ..with_log(60.0, "adaptive.jsonl")
+                },
+            ],
+        };
+        assert_eq!(
+            history.best_log_file(),
+            Some(dir.path().join("fast.jsonl").as_path())
+        );
+
+        history
+            .sessions
+            .retain(|session| session.average_wpm > 50.0);
+        assert_eq!(history.best_log_file(), None);
+    }
+
+    #[test]
+    fn test_migrations_cover_all_versions() {
+        assert_eq!(MIGRATIONS.len() as u32, SCHEMA_VERSION - 1);
+    }
+
+    #[test]
+    fn test_older_schema_migrated() {
+        // A version 2 that renamed `average_wpm`, to check the chain on a
+        // version 1 document.
+        fn rename_average_wpm(document: &mut Value) -> Result<()> {
+            let sessions = document["sessions"]
+                .as_array_mut()
+                .context("missing sessions")?;
+            for session in sessions {
+                let session = session
+                    .as_object_mut()
+                    .context("session is not an object")?;
+                let wpm = session.remove("average_wpm").unwrap_or(Value::Null);
+                session.insert("wpm".to_string(), wpm);
+            }
+            Ok(())
+        }
+        let v1 = serde_json::to_value(Document {
+            version: 1,
+            history: &History {
+                sessions: vec![record(2, 40.0)],
+            },
+        })
+        .unwrap();
+
+        let v2 = migrate(v1.clone(), &[rename_average_wpm]).unwrap();
+        assert_eq!(v2["version"], 2);
+        assert_eq!(v2["sessions"][0]["wpm"], 40.0);
+        assert!(v2["sessions"][0].get("average_wpm").is_none());
+        assert!(migrate(v2, &[rename_average_wpm]).is_ok());
+        assert_eq!(migrate(v1.clone(), MIGRATIONS).unwrap(), v1);
+    }
+
+    #[test]
+    fn test_newer_schema_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = HistoryStore::new(dir.path().join("history.json"));
+        fs::write(store.path(), r#"{"version": 999, "sessions": []}"#).unwrap();
+        assert!(store.load().is_err());
+        fs::write(store.path(), r#"{"sessions": []}"#).unwrap();
+        assert!(store.load().is_err());
+    }
+}
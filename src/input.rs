@@ -0,0 +1,434 @@
+//! Input events of the application and the keymap producing them.
+//!
+//! The terminal reports keys, which the [`Keymap`] turns into [`InputEvent`]s:
+//! what the user asks for, whatever the key they pressed. The application,
+//! session logs and tests only deal with events, so they do not depend on the
+//! terminal library.
+//!
+//! Every action can be rebound from the `[keys]` table of the config file,
+//! with one key or a list of keys per action:
+//!
+//! ```toml
+//! [keys]
+//! delete_word = ["ctrl+w", "alt+backspace"]
+//! pause = "f5"
+//! quit = "ctrl+q"
+//! ```
+//!
+//! Keys are named `space`, `backspace`, `delete`, `enter`, `esc`, `tab`,
+//! `shift+tab`, `f1` to `f12` or by their character, with optional `ctrl+`
+//! and `alt+` modifiers. Characters without modifiers that no action is bound
+//! to type themselves.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{Result, bail};
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// A command that neither types nor moves between word lists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Command {
+    /// Switch the keyboard heatmap between error rate and latency
+    ToggleHeatmap,
+    /// Start a new timed test once the current one is finished
+    Restart,
+}
+
+/// What the user asks the application to do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum InputEvent {
+    /// A character typed into the current word
+    Type(char),
+    DeleteChar,
+    /// Delete the whole input of the current word
+    DeleteWord,
+    /// Submit the current word
+    Submit,
+    NextList,
+    PrevList,
+    /// Pause the session, or resume it
+    Pause,
+    /// End the session
+    Quit,
+    Command(Command),
+}
+
+impl InputEvent {
+    /// The event of typing `c` with the default keymap, where a space
+    /// submits the word.
+    #[cfg(test)]
+    pub(crate) fn typed(c: char) -> Self {
+        if c == ' ' {
+            InputEvent::Submit
+        } else {
+            InputEvent::Type(c)
+        }
+    }
+}
+
+/// A key without its modifiers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum KeyName {
+    Char(char),
+    Backspace,
+    Delete,
+    Enter,
+    Esc,
+    Tab,
+    BackTab,
+    F(u8),
+}
+
+/// A key, with the Ctrl and Alt modifiers held when it was pressed.
+///
+/// Shift is part of the character typed, or of `BackTab`, so it is not a
+/// modifier of its own. Characters typed with Ctrl or Alt are lowercase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct KeyChord {
+    pub(crate) key: KeyName,
+    pub(crate) ctrl: bool,
+    pub(crate) alt: bool,
+}
+
+impl KeyChord {
+    pub(crate) fn new(key: KeyName, ctrl: bool, alt: bool) -> Self {
+        let key = match key {
+            KeyName::Char(c) if ctrl || alt => KeyName::Char(c.to_ascii_lowercase()),
+            key => key,
+        };
+        KeyChord { key, ctrl, alt }
+    }
+
+    /// The key alone, without modifiers.
+    pub(crate) fn key(key: KeyName) -> Self {
+        KeyChord::new(key, false, false)
+    }
+
+    fn ctrl(c: char) -> Self {
+        KeyChord::new(KeyName::Char(c), true, false)
+    }
+
+    /// The name of the chord shown on screen, such as `Ctrl+C`.
+    pub(crate) fn label(self) -> String {
+        let modifiers = match (self.ctrl, self.alt) {
+            (true, true) => "Ctrl+Alt+",
+            (true, false) => "Ctrl+",
+            (false, true) => "Alt+",
+            (false, false) => "",
+        };
+        let key = match self.key {
+            KeyName::Char(' ') => "Space".to_string(),
+            KeyName::Char(c) if self.ctrl || self.alt => c.to_uppercase().to_string(),
+            KeyName::Char(c) => c.to_string(),
+            KeyName::Backspace => "Backspace".to_string(),
+            KeyName::Delete => "Delete".to_string(),
+            KeyName::Enter => "Enter".to_string(),
+            KeyName::Esc => "Esc".to_string(),
+            KeyName::Tab => "Tab".to_string(),
+            KeyName::BackTab => "Shift+Tab".to_string(),
+            KeyName::F(n) => format!("F{n}"),
+        };
+        format!("{modifiers}{key}")
+    }
+}
+
+impl FromStr for KeyChord {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (mut ctrl, mut alt, mut shift) = (false, false, false);
+        let mut rest = s;
+        // A lone `+` is a key of its own, not a separator.
+        while let Some((modifier, key)) = rest.split_once('+').filter(|(_, key)| !key.is_empty()) {
+            match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" => ctrl = true,
+                "alt" => alt = true,
+                "shift" => shift = true,
+                _ => bail!("unknown modifier `{modifier}` in key `{s}`"),
+            }
+            rest = key;
+        }
+        let mut chars = rest.chars();
+        let key = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyName::Char(c),
+            _ => match rest.to_ascii_lowercase().as_str() {
+                "space" => KeyName::Char(' '),
+                "backspace" => KeyName::Backspace,
+                "delete" => KeyName::Delete,
+                "enter" => KeyName::Enter,
+                "esc" => KeyName::Esc,
+                "tab" if shift => KeyName::BackTab,
+                "tab" => KeyName::Tab,
+                name => match name.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    Some(n @ 1..=12) => KeyName::F(n),
+                    _ => bail!("unknown key `{s}`"),
+                },
+            },
+        };
+        if shift && key != KeyName::BackTab {
+            bail!("shift only combines with tab, type the shifted character in `{s}` instead");
+        }
+        Ok(KeyChord::new(key, ctrl, alt))
+    }
+}
+
+/// Written the way the config file names keys, such as `ctrl+c`.
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.ctrl {
+            write!(f, "ctrl+")?;
+        }
+        if self.alt {
+            write!(f, "alt+")?;
+        }
+        match self.key {
+            KeyName::Char(' ') => write!(f, "space"),
+            KeyName::Char(c) => write!(f, "{c}"),
+            KeyName::Backspace => write!(f, "backspace"),
+            KeyName::Delete => write!(f, "delete"),
+            KeyName::Enter => write!(f, "enter"),
+            KeyName::Esc => write!(f, "esc"),
+            KeyName::Tab => write!(f, "tab"),
+            KeyName::BackTab => write!(f, "shift+tab"),
+            KeyName::F(n) => write!(f, "f{n}"),
+        }
+    }
+}
+
+/// Deserialize one key or a list of keys.
+fn deserialize_keys<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<KeyChord>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Raw {
+        One(String),
+        Many(Vec<String>),
+    }
+    let names = match Raw::deserialize(deserializer)? {
+        Raw::One(name) => vec![name],
+        Raw::Many(names) => names,
+    };
+    names
+        .iter()
+        .map(|name| name.parse().map_err(serde::de::Error::custom))
+        .collect()
+}
+
+/// The keys bound to each action, as set in the `[keys]` table.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct KeyBindings {
+    #[serde(deserialize_with = "deserialize_keys")]
+    delete_char: Vec<KeyChord>,
+    #[serde(deserialize_with = "deserialize_keys")]
+    delete_word: Vec<KeyChord>,
+    #[serde(deserialize_with = "deserialize_keys")]
+    submit: Vec<KeyChord>,
+    #[serde(deserialize_with = "deserialize_keys")]
+    next_list: Vec<KeyChord>,
+    #[serde(deserialize_with = "deserialize_keys")]
+    prev_list: Vec<KeyChord>,
+    #[serde(deserialize_with = "deserialize_keys")]
+    pause: Vec<KeyChord>,
+    #[serde(deserialize_with = "deserialize_keys")]
+    quit: Vec<KeyChord>,
+    #[serde(deserialize_with = "deserialize_keys")]
+    toggle_heatmap: Vec<KeyChord>,
+    #[serde(deserialize_with = "deserialize_keys")]
+    restart: Vec<KeyChord>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings {
+            delete_char: vec![KeyChord::key(KeyName::Backspace)],
+            delete_word: vec![
+                KeyChord::ctrl('w'),
+                KeyChord::new(KeyName::Backspace, false, true),
+            ],
+            submit: vec![KeyChord::key(KeyName::Char(' '))],
+            next_list: vec![KeyChord::key(KeyName::Tab)],
+            prev_list: vec![KeyChord::key(KeyName::BackTab)],
+            pause: vec![KeyChord::key(KeyName::Esc)],
+            quit: vec![KeyChord::ctrl('c')],
+            toggle_heatmap: vec![KeyChord::key(KeyName::F(2))],
+            restart: vec![KeyChord::key(KeyName::Enter)],
+        }
+    }
+}
+
+impl KeyBindings {
+    /// Every action, with its config name and the keys bound to it.
+    fn actions(&self) -> [(&'static str, InputEvent, &[KeyChord]); 9] {
+        [
+            ("delete_char", InputEvent::DeleteChar, &self.delete_char),
+            ("delete_word", InputEvent::DeleteWord, &self.delete_word),
+            ("submit", InputEvent::Submit, &self.submit),
+            ("next_list", InputEvent::NextList, &self.next_list),
+            ("prev_list", InputEvent::PrevList, &self.prev_list),
+            ("pause", InputEvent::Pause, &self.pause),
+            ("quit", InputEvent::Quit, &self.quit),
+            (
+                "toggle_heatmap",
+                InputEvent::Command(Command::ToggleHeatmap),
+                &self.toggle_heatmap,
+            ),
+            (
+                "restart",
+                InputEvent::Command(Command::Restart),
+                &self.restart,
+            ),
+        ]
+    }
+}
+
+/// Turns keys into input events.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Keymap {
+    bindings: KeyBindings,
+    events: HashMap<KeyChord, InputEvent>,
+}
+
+impl Keymap {
+    /// The keymap of `bindings`.
+    ///
+    /// Fails if a key is bound to two actions, or if no key submits a word
+    /// or ends the session.
+    pub(crate) fn new(bindings: KeyBindings) -> Result<Self> {
+        let mut events = HashMap::new();
+        let mut names = HashMap::new();
+        for (name, event, keys) in bindings.actions() {
+            if keys.is_empty() && matches!(event, InputEvent::Submit | InputEvent::Quit) {
+                bail!("`{name}` needs at least one key");
+            }
+            for &key in keys {
+                if let Some(other) = names.insert(key, name) {
+                    bail!("`{key}` is bound to both `{other}` and `{name}`");
+                }
+                events.insert(key, event);
+            }
+        }
+        Ok(Keymap { bindings, events })
+    }
+
+    /// The event of pressing `key`, or `None` if it does nothing.
+    pub(crate) fn event(&self, key: KeyChord) -> Option<InputEvent> {
+        if let Some(&event) = self.events.get(&key) {
+            return Some(event);
+        }
+        match key.key {
+            // Spaces only submit words through their binding.
+            KeyName::Char(c) if c != ' ' && !key.ctrl && !key.alt => Some(InputEvent::Type(c)),
+            _ => None,
+        }
+    }
+
+    /// The label of the first key bound to `event`, to show in hints.
+    pub(crate) fn hint(&self, event: InputEvent) -> String {
+        self.bindings
+            .actions()
+            .into_iter()
+            .find(|(_, action, _)| *action == event)
+            .and_then(|(_, _, keys)| keys.first())
+            .map_or_else(|| "unbound".to_string(), |key| key.label())
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap::new(KeyBindings::default()).expect("the default bindings are valid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chord(s: &str) -> KeyChord {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_display_keys() {
+        for name in [
+            "ctrl+c",
+            "alt+backspace",
+            "shift+tab",
+            "space",
+            "f2",
+            "esc",
+            "q",
+            "+",
+        ] {
+            assert_eq!(chord(name).to_string(), name);
+        }
+        assert_eq!(chord("Ctrl+W"), KeyChord::ctrl('w'));
+        assert_eq!(chord("ctrl++").key, KeyName::Char('+'));
+        assert_eq!(chord("shift+tab").key, KeyName::BackTab);
+        assert!("f13".parse::<KeyChord>().is_err());
+        assert!("hyper+a".parse::<KeyChord>().is_err());
+        assert!("shift+a".parse::<KeyChord>().is_err());
+        assert!("pageup".parse::<KeyChord>().is_err());
+    }
+
+    #[test]
+    fn test_default_keymap() {
+        let keymap = Keymap::default();
+        assert_eq!(keymap.event(chord("a")), Some(InputEvent::Type('a')));
+        assert_eq!(keymap.event(chord("space")), Some(InputEvent::Submit));
+        assert_eq!(keymap.event(chord("ctrl+w")), Some(InputEvent::DeleteWord));
+        assert_eq!(
+            keymap.event(chord("f2")),
+            Some(InputEvent::Command(Command::ToggleHeatmap))
+        );
+        assert_eq!(keymap.event(chord("ctrl+x")), None);
+        assert_eq!(keymap.hint(InputEvent::Quit), "Ctrl+C");
+        assert_eq!(keymap.hint(InputEvent::PrevList), "Shift+Tab");
+    }
+
+    #[test]
+    fn test_rebound_keys() {
+        let bindings: KeyBindings =
+            toml::from_str("submit = [\"enter\"]\nrestart = []\nquit = \"q\"").unwrap();
+        let keymap = Keymap::new(bindings).unwrap();
+        assert_eq!(keymap.event(chord("enter")), Some(InputEvent::Submit));
+        assert_eq!(keymap.event(chord("q")), Some(InputEvent::Quit));
+        assert_eq!(keymap.event(chord("space")), None);
+        assert_eq!(keymap.event(chord("ctrl+c")), None);
+        assert_eq!(
+            keymap.hint(InputEvent::Command(Command::Restart)),
+            "unbound"
+        );
+
+        let keymap = |toml| {
+            toml::from_str(toml)
+                .map_err(anyhow::Error::from)
+                .and_then(Keymap::new)
+        };
+        assert!(keymap("pause = \"f2\"").is_err());
+        assert!(keymap("quit = []").is_err());
+        assert!(keymap("submit = \"hyper+space\"").is_err());
+        assert!(keymap("jump = \"j\"").is_err());
+    }
+
+    #[test]
+    fn test_events_serialize_for_logs() {
+        let events = [
+            InputEvent::Type('a'),
+            InputEvent::Submit,
+            InputEvent::Command(Command::ToggleHeatmap),
+        ];
+        let json = serde_json::to_string(&events).unwrap();
+        assert_eq!(
+            json,
+            r#"[{"type":"a"},"submit",{"command":"toggle_heatmap"}]"#
+        );
+        assert_eq!(
+            serde_json::from_str::<Vec<InputEvent>>(&json).unwrap(),
+            events
+        );
+    }
+}
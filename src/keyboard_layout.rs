@@ -0,0 +1,386 @@
+//! Keyboard layouts: key positions and finger assignment.
+//!
+//! A layout is a list of rows from the number row down, each row listing the
+//! characters of its keys from left to right. Unless a layout says otherwise,
+//! fingers follow the usual touch-typing columns: one column per finger from
+//! the left pinky, the two inner columns of each hand for the index fingers,
+//! and everything right of the right ring finger for the right pinky.
+//!
+//! Besides the built-in layouts, a layout can be loaded from a TOML file:
+//!
+//! ```toml
+//! name = "QWERTY"
+//! home_row = 2
+//! rows = ["1234567890-=", "qwertyuiop[]", "asdfghjkl;'", "zxcvbnm,./"]
+//! # Optional, one digit per key from 0 (left pinky) to 7 (right pinky).
+//! fingers = ["012334456777", "012334456777", "01233445677", "0123344567"]
+//! ```
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+
+/// The hand a finger belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Hand {
+    Left,
+    Right,
+}
+
+/// The eight typing fingers, from the left pinky to the right pinky.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Finger {
+    LeftPinky,
+    LeftRing,
+    LeftMiddle,
+    LeftIndex,
+    RightIndex,
+    RightMiddle,
+    RightRing,
+    RightPinky,
+}
+
+impl Finger {
+    pub(crate) const ALL: [Finger; 8] = [
+        Finger::LeftPinky,
+        Finger::LeftRing,
+        Finger::LeftMiddle,
+        Finger::LeftIndex,
+        Finger::RightIndex,
+        Finger::RightMiddle,
+        Finger::RightRing,
+        Finger::RightPinky,
+    ];
+
+    pub(crate) fn hand(self) -> Hand {
+        if self <= Finger::LeftIndex {
+            Hand::Left
+        } else {
+            Hand::Right
+        }
+    }
+
+    /// Two-letter abbreviation, such as `LP` for the left pinky.
+    pub fn short_name(self) -> &'static str {
+        match self {
+            Finger::LeftPinky => "LP",
+            Finger::LeftRing => "LR",
+            Finger::LeftMiddle => "LM",
+            Finger::LeftIndex => "LI",
+            Finger::RightIndex => "RI",
+            Finger::RightMiddle => "RM",
+            Finger::RightRing => "RR",
+            Finger::RightPinky => "RP",
+        }
+    }
+
+    /// The finger typing the key in `column` on a row-staggered keyboard.
+    fn for_column(column: usize) -> Finger {
+        match column {
+            0 => Finger::LeftPinky,
+            1 => Finger::LeftRing,
+            2 => Finger::LeftMiddle,
+            3 | 4 => Finger::LeftIndex,
+            5 | 6 => Finger::RightIndex,
+            7 => Finger::RightMiddle,
+            8 => Finger::RightRing,
+            _ => Finger::RightPinky,
+        }
+    }
+}
+
+/// A key of a layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Key {
+    pub(crate) char: char,
+    pub(crate) finger: Finger,
+}
+
+/// Key rows and finger assignment of a keyboard layout.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyboardLayout {
+    pub(crate) name: String,
+    /// Rows from top to bottom
+    pub(crate) rows: Vec<Vec<Key>>,
+    /// Index of the home row in `rows`
+    pub(crate) home_row: usize,
+}
+
+/// Names of the built-in layouts, as accepted by [`KeyboardLayout::builtin`].
+pub(crate) const BUILTIN_LAYOUTS: [&str; 6] = [
+    "dvorak",
+    "programmer-dvorak",
+    "colemak",
+    "colemak-dh",
+    "workman",
+    "qwerty",
+];
+
+impl Default for KeyboardLayout {
+    fn default() -> Self {
+        Self::builtin("dvorak").expect("dvorak is a built-in layout")
+    }
+}
+
+impl KeyboardLayout {
+    /// Build a layout with fingers assigned by column.
+    fn from_rows(name: &str, rows: &[&str], home_row: usize) -> Self {
+        let rows = rows
+            .iter()
+            .map(|row| {
+                row.chars()
+                    .enumerate()
+                    .map(|(column, char)| Key {
+                        char,
+                        finger: Finger::for_column(column),
+                    })
+                    .collect()
+            })
+            .collect();
+        KeyboardLayout {
+            name: name.to_string(),
+            rows,
+            home_row,
+        }
+    }
+
+    /// One of the built-in layouts: `dvorak`, `programmer-dvorak`, `colemak`,
+    /// `colemak-dh`, `workman` or `qwerty`.
+    pub fn builtin(name: &str) -> Option<Self> {
+        let (display_name, rows): (&str, [&str; 4]) = match name {
+            "dvorak" => (
+                "Dvorak",
+                ["1234567890[]", "',.pyfgcrl/=", "aoeuidhtns-", ";qjkxbmwvz"],
+            ),
+            "programmer-dvorak" => (
+                "Programmer Dvorak",
+                ["&[{}(=*)+]!#", ";,.pyfgcrl/@", "aoeuidhtns-", "'qjkxbmwvz"],
+            ),
+            "colemak" => (
+                "Colemak",
+                ["1234567890-=", "qwfpgjluy;[]", "arstdhneio'", "zxcvbkm,./"],
+            ),
+            "colemak-dh" => (
+                "Colemak-DH",
+                ["1234567890-=", "qwfpbjluy;[]", "arstgmneio'", "zxcdvkh,./"],
+            ),
+            "workman" => (
+                "Workman",
+                ["1234567890-=", "qdrwbjfup;[]", "ashtgyneoi'", "zxmcvkl,./"],
+            ),
+            "qwerty" => (
+                "QWERTY",
+                ["1234567890-=", "qwertyuiop[]", "asdfghjkl;'", "zxcvbnm,./"],
+            ),
+            _ => return None,
+        };
+        Some(Self::from_rows(display_name, &rows, 2))
+    }
+
+    /// Resolve a built-in layout name, or else load the layout file at that path.
+    pub fn resolve(name_or_path: &str) -> Result<Self> {
+        match Self::builtin(name_or_path) {
+            Some(layout) => Ok(layout),
+            None => Self::load(Path::new(name_or_path)).with_context(|| {
+                format!(
+                    "`{name_or_path}` is neither a built-in layout ({}) nor a readable layout file",
+                    BUILTIN_LAYOUTS.join(", ")
+                )
+            }),
+        }
+    }
+
+    /// Load a layout from a TOML file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        Self::parse(&contents).with_context(|| format!("invalid layout file {}", path.display()))
+    }
+
+    fn parse(contents: &str) -> Result<Self> {
+        let file: LayoutFile = toml::from_str(contents)?;
+        if file.rows.is_empty() {
+            bail!("a layout needs at least one row");
+        }
+        if file.home_row >= file.rows.len() {
+            bail!(
+                "home_row {} is out of range for {} rows",
+                file.home_row,
+                file.rows.len()
+            );
+        }
+
+        let row_refs: Vec<&str> = file.rows.iter().map(String::as_str).collect();
+        let mut layout = Self::from_rows(&file.name, &row_refs, file.home_row);
+        if let Some(fingers) = file.fingers {
+            if fingers.len() != layout.rows.len() {
+                bail!("fingers must have one entry per row");
+            }
+            for (index, (row, digits)) in layout.rows.iter_mut().zip(&fingers).enumerate() {
+                if digits.chars().count() != row.len() {
+                    bail!("fingers for row {index} must have one digit per key");
+                }
+                for (key, digit) in row.iter_mut().zip(digits.chars()) {
+                    key.finger = digit
+                        .to_digit(10)
+                        .and_then(|d| Finger::ALL.get(d as usize).copied())
+                        .with_context(|| {
+                            format!("finger `{digit}` in row {index} is not a digit from 0 to 7")
+                        })?;
+                }
+            }
+        }
+        Ok(layout)
+    }
+
+    /// The key producing `char`, ignoring letter case.
+    pub(crate) fn key(&self, char: char) -> Option<&Key> {
+        let char = char.to_ascii_lowercase();
+        self.rows.iter().flatten().find(|key| key.char == char)
+    }
+
+    /// The finger typing `char`, if the layout has a key for it.
+    pub fn finger(&self, char: char) -> Option<Finger> {
+        self.key(char).map(|key| key.finger)
+    }
+
+    /// The display name of the layout, such as `Dvorak`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// The TOML representation of a layout file.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LayoutFile {
+    name: String,
+    rows: Vec<String>,
+    home_row: usize,
+    fingers: Option<Vec<String>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_all_builtins_resolve() {
+        for name in BUILTIN_LAYOUTS {
+            let layout = KeyboardLayout::resolve(name).unwrap();
+            assert_eq!(layout.rows.len(), 4);
+            let letters = layout
+                .rows
+                .iter()
+                .flatten()
+                .filter(|key| key.char.is_ascii_lowercase())
+                .count();
+            assert_eq!(letters, 26, "{name} must have every letter once");
+        }
+    }
+
+    #[test]
+    fn test_dvorak_home_row() {
+        let layout = KeyboardLayout::default();
+        assert_eq!(layout.name, "Dvorak");
+        let home: String = layout.rows[layout.home_row]
+            .iter()
+            .map(|k| k.char)
+            .collect();
+        assert_eq!(home, "aoeuidhtns-");
+    }
+
+    #[test]
+    fn test_fingers_by_column() {
+        let layout = KeyboardLayout::default();
+        assert_eq!(layout.finger('a'), Some(Finger::LeftPinky));
+        assert_eq!(layout.finger('u'), Some(Finger::LeftIndex));
+        assert_eq!(layout.finger('i'), Some(Finger::LeftIndex));
+        assert_eq!(layout.finger('d'), Some(Finger::RightIndex));
+        assert_eq!(layout.finger('S'), Some(Finger::RightPinky));
+        assert_eq!(layout.finger('-'), Some(Finger::RightPinky));
+        assert_eq!(layout.finger('é'), None);
+        assert_eq!(Finger::LeftIndex.hand(), Hand::Left);
+        assert_eq!(Finger::RightIndex.hand(), Hand::Right);
+    }
+
+    #[test]
+    fn test_layouts_place_keys_differently() {
+        let qwerty = KeyboardLayout::builtin("qwerty").unwrap();
+        let colemak = KeyboardLayout::builtin("colemak").unwrap();
+        assert_eq!(qwerty.finger('e'), Some(Finger::LeftMiddle));
+        assert_eq!(colemak.finger('e'), Some(Finger::RightMiddle));
+    }
+
+    #[test]
+    fn test_parse_layout_file() {
+        let layout = KeyboardLayout::parse(
+            r#"
+            name = "Tiny"
+            home_row = 0
+            rows = ["abcd", "efgh"]
+            fingers = ["0011", "7766"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(layout.name, "Tiny");
+        assert_eq!(layout.finger('b'), Some(Finger::LeftPinky));
+        assert_eq!(layout.finger('c'), Some(Finger::LeftRing));
+        assert_eq!(layout.finger('h'), Some(Finger::RightRing));
+    }
+
+    #[test]
+    fn test_parse_invalid_layout_files() {
+        assert!(
+            KeyboardLayout::parse(
+                r#"name = "x"
+home_row = 0
+rows = []"#
+            )
+            .is_err()
+        );
+        assert!(
+            KeyboardLayout::parse(
+                r#"name = "x"
+home_row = 3
+rows = ["ab"]"#
+            )
+            .is_err()
+        );
+        assert!(
+            KeyboardLayout::parse(
+                r#"name = "x"
+home_row = 0
+rows = ["ab"]
+fingers = ["0"]"#
+            )
+            .is_err()
+        );
+        assert!(
+            KeyboardLayout::parse(
+                r#"name = "x"
+home_row = 0
+rows = ["ab"]
+fingers = ["09"]"#
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_load_layout_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mine.toml");
+        fs::write(
+            &path,
+            "name = \"Mine\"\nhome_row = 0\nrows = [\"qwerty\"]\n",
+        )
+        .unwrap();
+
+        let layout = KeyboardLayout::resolve(path.to_str().unwrap()).unwrap();
+        assert_eq!(layout.name, "Mine");
+        assert!(KeyboardLayout::resolve("no-such-layout").is_err());
+    }
+}
@@ -0,0 +1,225 @@
+//! Progressive lessons generated from a keyboard layout and a dictionary.
+//!
+//! An unlock schedule lists the keys introduced at each level. The words of a
+//! level only use keys unlocked so far, and each one uses at least one of the
+//! keys introduced by that level, so that every level practices its new keys.
+//!
+//! Generated levels are written as gzipped word lists, one word per line, in
+//! the format of the embedded lessons.
+
+use std::collections::BTreeSet;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use flate2::Compression;
+use flate2::write::GzEncoder;
+
+use crate::keyboard_layout::KeyboardLayout;
+
+/// The keys introduced at each level, in order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct UnlockSchedule {
+    levels: Vec<Vec<char>>,
+}
+
+impl UnlockSchedule {
+    /// The letters of the home row, then of the other rows by distance from the
+    /// home row, the row above first.
+    pub(crate) fn for_layout(layout: &KeyboardLayout) -> Self {
+        let mut rows: Vec<usize> = (0..layout.rows.len()).collect();
+        rows.sort_by_key(|&row| {
+            let distance = row.abs_diff(layout.home_row);
+            (distance, row > layout.home_row)
+        });
+        let levels = rows
+            .into_iter()
+            .map(|row| {
+                layout.rows[row]
+                    .iter()
+                    .map(|key| key.char)
+                    .filter(char::is_ascii_alphabetic)
+                    .collect::<Vec<char>>()
+            })
+            .filter(|keys| !keys.is_empty())
+            .collect();
+        UnlockSchedule { levels }
+    }
+
+    /// Parse a comma-separated list of key groups, such as `aoeu,idhtns,pyfg`.
+    ///
+    /// Every key must be on `layout` and appear only once.
+    pub(crate) fn parse(spec: &str, layout: &KeyboardLayout) -> Result<Self> {
+        let mut seen = BTreeSet::new();
+        let mut levels = Vec::new();
+        for (index, group) in spec.split(',').enumerate() {
+            let keys: Vec<char> = group.trim().chars().collect();
+            if keys.is_empty() {
+                bail!("level {} of the unlock schedule has no keys", index + 1);
+            }
+            for &key in &keys {
+                if layout.key(key).is_none() {
+                    bail!("key `{key}` is not on the {} layout", layout.name);
+                }
+                if !seen.insert(key) {
+                    bail!("key `{key}` is unlocked more than once");
+                }
+            }
+            levels.push(keys);
+        }
+        Ok(UnlockSchedule { levels })
+    }
+
+    /// The keys introduced at each level.
+    pub(crate) fn levels(&self) -> &[Vec<char>] {
+        &self.levels
+    }
+}
+
+/// The words of one generated level.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Lesson {
+    /// Keys introduced by this level
+    pub(crate) new_keys: Vec<char>,
+    /// Sorted, without duplicates
+    pub(crate) words: Vec<String>,
+}
+
+/// Split `dictionary` into one lesson per level of `schedule`.
+///
+/// Words using a key that is never unlocked, including upper case letters
+/// when only lower case keys are scheduled, are left out. Fails if a level
+/// ends up without any word.
+pub(crate) fn generate_lessons(
+    dictionary: &[String],
+    schedule: &UnlockSchedule,
+) -> Result<Vec<Lesson>> {
+    let mut unlocked = BTreeSet::new();
+    let mut lessons = Vec::new();
+    for (index, new_keys) in schedule.levels().iter().enumerate() {
+        unlocked.extend(new_keys.iter().copied());
+        let words: BTreeSet<&String> = dictionary
+            .iter()
+            .filter(|word| word.chars().all(|c| unlocked.contains(&c)))
+            .filter(|word| word.chars().any(|c| new_keys.contains(&c)))
+            .collect();
+        if words.is_empty() {
+            bail!(
+                "no dictionary word fits level {} (new keys `{}`)",
+                index + 1,
+                new_keys.iter().collect::<String>()
+            );
+        }
+        lessons.push(Lesson {
+            new_keys: new_keys.clone(),
+            words: words.into_iter().cloned().collect(),
+        });
+    }
+    Ok(lessons)
+}
+
+/// Write each lesson to `dir` as `level<N>.txt.gz`, creating `dir` if needed.
+///
+/// Returns the paths of the written files.
+pub(crate) fn write_lessons(lessons: &[Lesson], dir: &Path) -> Result<Vec<PathBuf>> {
+    fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    lessons
+        .iter()
+        .enumerate()
+        .map(|(index, lesson)| {
+            let path = dir.join(format!("level{}.txt.gz", index + 1));
+            write_gzip_word_list(&path, &lesson.words)
+                .with_context(|| format!("failed to write {}", path.display()))?;
+            Ok(path)
+        })
+        .collect()
+}
+
+fn write_gzip_word_list(path: &Path, words: &[String]) -> Result<()> {
+    let mut encoder = GzEncoder::new(File::create(path)?, Compression::best());
+    for word in words {
+        writeln!(encoder, "{word}")?;
+    }
+    encoder.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::word_lists::load_word_lists;
+
+    fn words(list: &[&str]) -> Vec<String> {
+        list.iter().map(|word| word.to_string()).collect()
+    }
+
+    #[test]
+    fn test_schedule_for_layout() {
+        let schedule = UnlockSchedule::for_layout(&KeyboardLayout::default());
+        let levels: Vec<String> = schedule
+            .levels()
+            .iter()
+            .map(|keys| keys.iter().collect())
+            .collect();
+        assert_eq!(levels, ["aoeuidhtns", "pyfgcrl", "qjkxbmwvz"]);
+    }
+
+    #[test]
+    fn test_parse_schedule() {
+        let layout = KeyboardLayout::default();
+        let schedule = UnlockSchedule::parse("aoeu, idhtns", &layout).unwrap();
+        assert_eq!(schedule.levels().len(), 2);
+        assert_eq!(schedule.levels()[1], ['i', 'd', 'h', 't', 'n', 's']);
+
+        assert!(UnlockSchedule::parse("aoeu,,id", &layout).is_err());
+        assert!(UnlockSchedule::parse("aoeu,ua", &layout).is_err());
+        assert!(UnlockSchedule::parse("aoeé", &layout).is_err());
+    }
+
+    #[test]
+    fn test_generate_lessons() {
+        let layout = KeyboardLayout::default();
+        let schedule = UnlockSchedule::parse("aoeu,ht,s", &layout).unwrap();
+        let dictionary = words(&[
+            "hat", "eau", "the", "hats", "oath", "Hat", "eau", "ease", "dog", "heat",
+        ]);
+
+        let lessons = generate_lessons(&dictionary, &schedule).unwrap();
+        assert_eq!(lessons[0].words, ["eau"]);
+        assert_eq!(lessons[1].words, ["hat", "heat", "oath", "the"]);
+        assert_eq!(lessons[2].words, ["ease", "hats"]);
+        assert_eq!(lessons[2].new_keys, ['s']);
+    }
+
+    #[test]
+    fn test_empty_level_is_an_error() {
+        let layout = KeyboardLayout::default();
+        let schedule = UnlockSchedule::parse("aoeu,q", &layout).unwrap();
+        assert!(generate_lessons(&words(&["eau", "hat"]), &schedule).is_err());
+    }
+
+    #[test]
+    fn test_written_lessons_load_as_word_lists() {
+        let dir = tempfile::tempdir().unwrap();
+        let lessons = vec![
+            Lesson {
+                new_keys: vec!['a'],
+                words: words(&["aa", "aaa"]),
+            },
+            Lesson {
+                new_keys: vec!['o'],
+                words: words(&["oa"]),
+            },
+        ];
+
+        let paths = write_lessons(&lessons, dir.path()).unwrap();
+        assert_eq!(paths[1], dir.path().join("level2.txt.gz"));
+
+        let lists = load_word_lists(&[dir.path().to_path_buf()]).unwrap();
+        let generated = &lists[lists.len() - 2..];
+        assert_eq!(generated[0].name, "level1");
+        assert_eq!(generated[0].words, ["aa", "aaa"]);
+        assert_eq!(generated[1].words, ["oa"]);
+    }
+}
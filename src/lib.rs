@@ -1,92 +1,48 @@
 //! Dvorak typing practice application for the terminal.
 //!
 //! End-user documentation lives in the
-//! [README](https://github.com/graelo/dvoratt#readme).
+//! [README](https://github.com/graelo/dvoratt#readme). The [`engine`] module
+//! is a headless typing engine for other programs, available without the
+//! default `tui` feature, set up with the [`KeyboardLayout`] and
+//! [`MasteryPolicy`] of the session.
 
-use anyhow::Result;
-use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyModifiers},
-    execute,
-    terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
-};
-use ratatui::{Terminal, backend::CrosstermBackend};
-use std::time::{Duration, Instant};
+// Without the terminal application, parts of the shared modules only serve
+// the history and reports it writes.
+#![cfg_attr(not(feature = "tui"), allow(dead_code))]
 
+mod adaptive;
+#[cfg(feature = "tui")]
 mod app;
+#[cfg(feature = "tui")]
+mod cli;
+#[cfg(feature = "tui")]
+mod clock;
+#[cfg(feature = "tui")]
+mod config;
+pub mod engine;
+#[cfg(feature = "tui")]
+mod ghost;
+mod history;
+#[cfg(feature = "tui")]
+mod input;
+mod keyboard_layout;
+mod lesson_generator;
 mod performance;
+mod report;
+mod review;
+#[cfg(feature = "tui")]
+mod session_log;
+#[cfg(feature = "tui")]
+mod theme;
+mod timed_test;
+#[cfg(feature = "tui")]
+mod tui;
+#[cfg(feature = "tui")]
 mod ui;
 mod word_lists;
 mod word_queue;
 
-use crate::app::App;
-
-/// Run the Dvorak typing practice application.
-pub fn run() -> Result<()> {
-    enable_raw_mode()?;
-    let mut stdout = std::io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
-
-    let mut app = App::new();
-    let res = run_app(&mut terminal, &mut app);
-
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
-    terminal.show_cursor()?;
-
-    match res {
-        Ok(true) => println!("{}", app.generate_final_scores()),
-        Ok(false) => {}
-        Err(err) => println!("{err:?}"),
-    }
-
-    Ok(())
-}
-
-fn run_app<B: ratatui::backend::Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<bool>
-where
-    B::Error: 'static + Send + Sync,
-{
-    let mut last_tick = Instant::now();
-    let tick_rate = Duration::from_millis(250);
-
-    loop {
-        terminal
-            .draw(|f| ui::draw(f, app))
-            .map_err(anyhow::Error::new)?;
-
-        let timeout = tick_rate
-            .checked_sub(last_tick.elapsed())
-            .unwrap_or(Duration::ZERO);
-
-        if crossterm::event::poll(timeout)?
-            && let Event::Key(key) = event::read()?
-        {
-            match key.code {
-                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                    return Ok(true);
-                }
-                KeyCode::Tab => {
-                    let next_index = (app.current_list_index + 1) % app.word_lists.len();
-                    app.change_word_list(next_index);
-                }
-                KeyCode::BackTab => {
-                    let next_index =
-                        (app.current_list_index + app.word_lists.len() - 1) % app.word_lists.len();
-                    app.change_word_list(next_index);
-                }
-                _ => app.on_key(key.code),
-            }
-        }
-
-        if last_tick.elapsed() >= tick_rate {
-            app.on_tick();
-            last_tick = Instant::now();
-        }
-    }
-}
+pub use crate::keyboard_layout::{Finger, KeyboardLayout};
+pub use crate::performance::{LearnedSpeed, MasteryPolicy};
+#[cfg(feature = "tui")]
+pub use crate::tui::run;
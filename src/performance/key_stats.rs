@@ -0,0 +1,188 @@
+//! Per-key accuracy and latency statistics.
+//!
+//! Every keystroke is attributed to the character that was expected at that
+//! position, so a miss on `e` counts against `e` whichever key was actually
+//! pressed. Latencies are the time since the previous keystroke and are only
+//! sampled for hits, as they measure how quickly the right key is found.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::keyboard_layout::{Finger, KeyboardLayout};
+
+/// Aggregated statistics for one expected key.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct KeyStatsEntry {
+    pub(crate) hits: u32,
+    pub(crate) misses: u32,
+    latencies: Vec<Duration>,
+}
+
+impl KeyStatsEntry {
+    /// Number of times the key was expected.
+    pub(crate) fn attempts(&self) -> u32 {
+        self.hits + self.misses
+    }
+
+    /// Fraction of attempts that were misses, between 0 and 1.
+    pub(crate) fn error_rate(&self) -> f32 {
+        if self.attempts() == 0 {
+            return 0.0;
+        }
+        self.misses as f32 / self.attempts() as f32
+    }
+
+    pub(crate) fn mean_latency(&self) -> Option<Duration> {
+        if self.latencies.is_empty() {
+            return None;
+        }
+        Some(self.latencies.iter().sum::<Duration>() / self.latencies.len() as u32)
+    }
+
+    /// The 90th percentile latency, using the nearest-rank method.
+    pub(crate) fn p90_latency(&self) -> Option<Duration> {
+        if self.latencies.is_empty() {
+            return None;
+        }
+        let mut sorted = self.latencies.clone();
+        sorted.sort_unstable();
+        let rank = (sorted.len() * 9).div_ceil(10);
+        Some(sorted[rank - 1])
+    }
+
+    fn merge(&mut self, other: &KeyStatsEntry) {
+        self.hits += other.hits;
+        self.misses += other.misses;
+        self.latencies.extend_from_slice(&other.latencies);
+    }
+}
+
+/// Statistics for every key expected during a session.
+#[derive(Debug, Default)]
+pub(crate) struct KeyStats {
+    keys: BTreeMap<char, KeyStatsEntry>,
+}
+
+impl KeyStats {
+    /// Record one keystroke where `expected` was the key to press.
+    ///
+    /// `latency` is the time since the previous keystroke, if there was one.
+    pub(crate) fn record(&mut self, expected: char, correct: bool, latency: Option<Duration>) {
+        let entry = self.keys.entry(expected).or_default();
+        if correct {
+            entry.hits += 1;
+            entry.latencies.extend(latency);
+        } else {
+            entry.misses += 1;
+        }
+    }
+
+    pub(crate) fn get(&self, key: char) -> Option<&KeyStatsEntry> {
+        self.keys.get(&key)
+    }
+
+    /// All keys seen so far, in character order.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (char, &KeyStatsEntry)> {
+        self.keys.iter().map(|(key, entry)| (*key, entry))
+    }
+
+    /// Statistics of the keys typed by each finger of `layout`.
+    ///
+    /// Keys absent from the layout are ignored, and fingers without any typed
+    /// key are left out.
+    pub(crate) fn by_finger(&self, layout: &KeyboardLayout) -> BTreeMap<Finger, KeyStatsEntry> {
+        let mut fingers: BTreeMap<Finger, KeyStatsEntry> = BTreeMap::new();
+        for (key, entry) in self.iter() {
+            if let Some(finger) = layout.finger(key) {
+                fingers.entry(finger).or_default().merge(entry);
+            }
+        }
+        fingers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Option<Duration> {
+        Some(Duration::from_millis(millis))
+    }
+
+    #[test]
+    fn test_hits_and_misses() {
+        let mut stats = KeyStats::default();
+        stats.record('a', true, ms(100));
+        stats.record('a', false, ms(300));
+        stats.record('a', true, None);
+        stats.record('o', false, ms(50));
+
+        let a = stats.get('a').unwrap();
+        assert_eq!((a.hits, a.misses, a.attempts()), (2, 1, 3));
+        assert!((a.error_rate() - 1.0 / 3.0).abs() < f32::EPSILON);
+        assert_eq!(stats.get('o').unwrap().error_rate(), 1.0);
+        assert!(stats.get('e').is_none());
+    }
+
+    #[test]
+    fn test_latency_only_sampled_for_hits() {
+        let mut stats = KeyStats::default();
+        stats.record('e', true, ms(100));
+        stats.record('e', true, ms(200));
+        stats.record('e', false, ms(900));
+
+        let e = stats.get('e').unwrap();
+        assert_eq!(e.mean_latency(), ms(150));
+
+        stats.record('u', false, ms(100));
+        assert_eq!(stats.get('u').unwrap().mean_latency(), None);
+        assert_eq!(stats.get('u').unwrap().p90_latency(), None);
+    }
+
+    #[test]
+    fn test_p90_latency() {
+        let mut stats = KeyStats::default();
+        for millis in (1..=10).rev() {
+            stats.record('h', true, ms(millis * 100));
+        }
+        assert_eq!(stats.get('h').unwrap().p90_latency(), ms(900));
+
+        stats.record('t', true, ms(250));
+        assert_eq!(stats.get('t').unwrap().p90_latency(), ms(250));
+    }
+
+    #[test]
+    fn test_by_finger() {
+        let mut stats = KeyStats::default();
+        stats.record('u', true, ms(100));
+        stats.record('i', false, None);
+        stats.record('i', true, ms(300));
+        stats.record('a', true, ms(50));
+        stats.record('é', true, ms(50));
+
+        let dvorak = KeyboardLayout::default();
+        let fingers = stats.by_finger(&dvorak);
+        assert_eq!(fingers.len(), 2);
+        let index = &fingers[&Finger::LeftIndex];
+        assert_eq!((index.hits, index.misses), (2, 1));
+        assert_eq!(index.mean_latency(), ms(200));
+        assert_eq!(fingers[&Finger::LeftPinky].hits, 1);
+
+        // On QWERTY, `u` and `i` belong to different fingers.
+        let qwerty = KeyboardLayout::builtin("qwerty").unwrap();
+        let fingers = stats.by_finger(&qwerty);
+        assert_eq!(fingers[&Finger::RightIndex].hits, 1);
+        assert_eq!(fingers[&Finger::RightMiddle].misses, 1);
+    }
+
+    #[test]
+    fn test_iter_in_key_order() {
+        let mut stats = KeyStats::default();
+        stats.record('u', true, None);
+        stats.record('a', true, None);
+        stats.record('e', true, None);
+
+        let keys: Vec<char> = stats.iter().map(|(key, _)| key).collect();
+        assert_eq!(keys, ['a', 'e', 'u']);
+    }
+}
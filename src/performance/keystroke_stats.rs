@@ -0,0 +1,174 @@
+//! Keystroke-level accuracy and speed metrics.
+//!
+//! Every typed character is compared with the expected one, which gives the
+//! accuracy and the raw and net typing speed over a span of time.
+
+use std::time::Duration;
+
+/// Counts of typed keystrokes, typing errors and completed words.
+///
+/// Errors erased with backspace are *corrected*; errors still present when a
+/// word is submitted are *uncorrected*.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub(crate) struct KeystrokeStats {
+    pub(crate) total_keystrokes: u32,
+    pub(crate) correct_keystrokes: u32,
+    pub(crate) corrected_errors: u32,
+    pub(crate) uncorrected_errors: u32,
+    pub(crate) words_typed: u32,
+}
+
+impl KeystrokeStats {
+    pub(crate) fn record_keystroke(&mut self, correct: bool) {
+        self.total_keystrokes += 1;
+        if correct {
+            self.correct_keystrokes += 1;
+        }
+    }
+
+    pub(crate) fn record_corrected_error(&mut self) {
+        self.corrected_errors += 1;
+    }
+
+    /// Count the characters of `input` that differ from `expected` when it is
+    /// submitted, including missing and extra characters, or count the word
+    /// as typed if there are none.
+    pub(crate) fn record_submitted_word(&mut self, input: &str, expected: &str) {
+        if input == expected {
+            self.words_typed += 1;
+            return;
+        }
+        let mismatched = input
+            .chars()
+            .zip(expected.chars())
+            .filter(|(typed, wanted)| typed != wanted)
+            .count();
+        let length_difference = input.chars().count().abs_diff(expected.chars().count());
+        self.uncorrected_errors += (mismatched + length_difference) as u32;
+    }
+
+    /// The counts accumulated since `baseline` was taken.
+    pub(crate) fn since(&self, baseline: &Self) -> Self {
+        KeystrokeStats {
+            total_keystrokes: self.total_keystrokes - baseline.total_keystrokes,
+            correct_keystrokes: self.correct_keystrokes - baseline.correct_keystrokes,
+            corrected_errors: self.corrected_errors - baseline.corrected_errors,
+            uncorrected_errors: self.uncorrected_errors - baseline.uncorrected_errors,
+            words_typed: self.words_typed - baseline.words_typed,
+        }
+    }
+
+    /// Percentage of keystrokes that matched the expected character.
+    pub(crate) fn accuracy(&self) -> f32 {
+        if self.total_keystrokes == 0 {
+            return 0.0;
+        }
+        self.correct_keystrokes as f32 * 100.0 / self.total_keystrokes as f32
+    }
+
+    /// Speed counting every keystroke, with five keystrokes per word.
+    pub(crate) fn raw_wpm(&self, elapsed: Duration) -> f32 {
+        let minutes = elapsed.as_secs_f32() / 60.0;
+        if minutes == 0.0 {
+            return 0.0;
+        }
+        (self.total_keystrokes as f32 / 5.0) / minutes
+    }
+
+    /// Raw speed minus one word per minute for each uncorrected error.
+    pub(crate) fn net_wpm(&self, elapsed: Duration) -> f32 {
+        let minutes = elapsed.as_secs_f32() / 60.0;
+        if minutes == 0.0 {
+            return 0.0;
+        }
+        (self.raw_wpm(elapsed) - self.uncorrected_errors as f32 / minutes).max(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accuracy() {
+        let mut stats = KeystrokeStats::default();
+        assert_eq!(stats.accuracy(), 0.0);
+
+        stats.record_keystroke(true);
+        stats.record_keystroke(true);
+        stats.record_keystroke(true);
+        stats.record_keystroke(false);
+        assert_eq!(stats.accuracy(), 75.0);
+    }
+
+    #[test]
+    fn test_record_submitted_word() {
+        let mut stats = KeystrokeStats::default();
+        stats.record_submitted_word("hello", "hello");
+        assert_eq!(stats.uncorrected_errors, 0);
+        assert_eq!(stats.words_typed, 1);
+
+        stats.record_submitted_word("hallo", "hello");
+        assert_eq!(stats.uncorrected_errors, 1);
+
+        stats.record_submitted_word("hel", "hello");
+        assert_eq!(stats.uncorrected_errors, 3);
+
+        stats.record_submitted_word("helloo", "hello");
+        assert_eq!(stats.uncorrected_errors, 4);
+        assert_eq!(stats.words_typed, 1);
+    }
+
+    #[test]
+    fn test_raw_and_net_wpm() {
+        let stats = KeystrokeStats {
+            total_keystrokes: 250,
+            correct_keystrokes: 240,
+            uncorrected_errors: 5,
+            ..KeystrokeStats::default()
+        };
+        let minute = Duration::from_secs(60);
+        assert_eq!(stats.raw_wpm(minute), 50.0);
+        assert_eq!(stats.net_wpm(minute), 45.0);
+        assert_eq!(stats.raw_wpm(Duration::ZERO), 0.0);
+    }
+
+    #[test]
+    fn test_net_wpm_not_negative() {
+        let stats = KeystrokeStats {
+            total_keystrokes: 5,
+            uncorrected_errors: 5,
+            ..KeystrokeStats::default()
+        };
+        assert_eq!(stats.net_wpm(Duration::from_secs(60)), 0.0);
+    }
+
+    #[test]
+    fn test_since() {
+        let baseline = KeystrokeStats {
+            total_keystrokes: 10,
+            correct_keystrokes: 8,
+            corrected_errors: 1,
+            uncorrected_errors: 1,
+            words_typed: 2,
+        };
+        let current = KeystrokeStats {
+            total_keystrokes: 25,
+            correct_keystrokes: 20,
+            corrected_errors: 2,
+            uncorrected_errors: 3,
+            words_typed: 4,
+        };
+        let window = current.since(&baseline);
+        assert_eq!(
+            window,
+            KeystrokeStats {
+                total_keystrokes: 15,
+                correct_keystrokes: 12,
+                corrected_errors: 1,
+                uncorrected_errors: 2,
+                words_typed: 2,
+            }
+        );
+    }
+}
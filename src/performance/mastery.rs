@@ -0,0 +1,166 @@
+//! The policy deciding when a problem word is mastered.
+//!
+//! A problem word is repeated a number of times in a row, and leaves the
+//! problem words once it has been typed fast enough without a mistake several
+//! times in a row. The speed is either a fixed number of words per minute or a
+//! percentage of the user's own average speed over the session, so that the
+//! same policy suits beginners and advanced typists.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::problem_words::ProblemWordEntry;
+
+/// The speed a problem word must be typed at to be mastered.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LearnedSpeed {
+    /// At least this many WPM
+    Wpm(f32),
+    /// At least this percentage of the average speed of the session
+    PercentOfAverage(f32),
+}
+
+impl LearnedSpeed {
+    /// The speed to reach, in WPM, when the session averages `average_wpm`.
+    pub(crate) fn threshold(self, average_wpm: f32) -> f32 {
+        match self {
+            LearnedSpeed::Wpm(wpm) => wpm,
+            LearnedSpeed::PercentOfAverage(percent) => average_wpm * percent / 100.0,
+        }
+    }
+}
+
+/// Serialized as a number of WPM, or as a string such as `"90%"`.
+impl Serialize for LearnedSpeed {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            LearnedSpeed::Wpm(wpm) => serializer.serialize_f32(*wpm),
+            LearnedSpeed::PercentOfAverage(percent) => {
+                serializer.serialize_str(&format!("{percent}%"))
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for LearnedSpeed {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Wpm(f32),
+            Text(String),
+        }
+        let speed = match Raw::deserialize(deserializer)? {
+            Raw::Wpm(wpm) => LearnedSpeed::Wpm(wpm),
+            Raw::Text(text) => text
+                .strip_suffix('%')
+                .and_then(|percent| percent.trim().parse().ok())
+                .map(LearnedSpeed::PercentOfAverage)
+                .ok_or_else(|| {
+                    serde::de::Error::custom(format!(
+                        "`{text}` is neither a speed in WPM nor a percentage such as \"90%\""
+                    ))
+                })?,
+        };
+        match speed {
+            LearnedSpeed::Wpm(value) | LearnedSpeed::PercentOfAverage(value)
+                if !value.is_finite() || value < 0.0 =>
+            {
+                Err(serde::de::Error::custom("the speed cannot be negative"))
+            }
+            speed => Ok(speed),
+        }
+    }
+}
+
+/// When problem words are mastered, and how often they are repeated.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MasteryPolicy {
+    /// Times a problem word is typed in a row
+    pub repetitions: u8,
+    /// Speed a problem word must be typed at
+    pub speed: LearnedSpeed,
+    /// Error-free attempts in a row a problem word needs
+    pub streak: u8,
+    /// Whether correcting a word with Backspace makes it a problem word
+    pub backspace_fails: bool,
+}
+
+impl Default for MasteryPolicy {
+    fn default() -> Self {
+        MasteryPolicy {
+            repetitions: 3,
+            speed: LearnedSpeed::Wpm(30.0),
+            streak: 2,
+            backspace_fails: true,
+        }
+    }
+}
+
+impl MasteryPolicy {
+    /// Whether a word typed at `speed` is fast enough, when the session
+    /// averages `average_wpm`.
+    pub(crate) fn is_fast_enough(&self, speed: f32, average_wpm: f32) -> bool {
+        speed >= self.speed.threshold(average_wpm)
+    }
+
+    /// Whether a problem word is mastered.
+    pub(crate) fn is_mastered(&self, entry: &ProblemWordEntry, average_wpm: f32) -> bool {
+        self.is_fast_enough(entry.avg_speed, average_wpm) && entry.correct_attempts >= self.streak
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(avg_speed: f32, correct_attempts: u8) -> ProblemWordEntry {
+        ProblemWordEntry {
+            word: "word".to_string(),
+            avg_speed,
+            backspaces: 0,
+            correct_attempts,
+        }
+    }
+
+    #[test]
+    fn test_default_policy() {
+        let policy = MasteryPolicy::default();
+        assert!(policy.is_mastered(&entry(30.0, 2), 80.0));
+        assert!(!policy.is_mastered(&entry(29.0, 2), 10.0));
+        assert!(!policy.is_mastered(&entry(40.0, 1), 10.0));
+    }
+
+    #[test]
+    fn test_relative_speed() {
+        let policy = MasteryPolicy {
+            speed: LearnedSpeed::PercentOfAverage(90.0),
+            ..MasteryPolicy::default()
+        };
+        assert!(policy.is_mastered(&entry(45.0, 2), 50.0));
+        assert!(!policy.is_mastered(&entry(44.0, 2), 50.0));
+        assert!(policy.is_fast_enough(20.0, 20.0));
+        assert!(!policy.is_fast_enough(20.0, 80.0));
+    }
+
+    #[test]
+    fn test_learned_speed_serde() {
+        let policy: MasteryPolicy =
+            toml::from_str("speed = \"85%\"\nstreak = 3\nbackspace_fails = false").unwrap();
+        assert_eq!(policy.speed, LearnedSpeed::PercentOfAverage(85.0));
+        assert_eq!(policy.streak, 3);
+        assert_eq!(policy.repetitions, 3);
+        assert!(!policy.backspace_fails);
+        let json = serde_json::to_string(&policy).unwrap();
+        assert!(json.contains("\"speed\":\"85%\""));
+        assert_eq!(
+            serde_json::from_str::<MasteryPolicy>(&json).unwrap(),
+            policy
+        );
+
+        let policy: MasteryPolicy = toml::from_str("speed = 45").unwrap();
+        assert_eq!(policy.speed, LearnedSpeed::Wpm(45.0));
+        assert!(toml::from_str::<MasteryPolicy>("speed = \"fast\"").is_err());
+        assert!(toml::from_str::<MasteryPolicy>("speed = -5").is_err());
+    }
+}
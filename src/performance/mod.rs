@@ -2,18 +2,26 @@
 //!
 //! This module contains components for tracking and analyzing user performance:
 //! - Word speed tracking (WPM calculation)
-//! - Problem word identification and management
+//! - Problem word identification and management, under a mastery policy
 //! - Struggle combination detection
 //! - Fastest/slowest word statistics
+//! - Keystroke accuracy and raw/net WPM
+//! - Per-key hit/miss counts and latencies
 //!
 //! The main entry point is the `PerformanceTracker` struct which aggregates
 //! all performance metrics during a typing session.
 
 mod fastest_slowest_words;
+mod key_stats;
+mod keystroke_stats;
+mod mastery;
 mod problem_words;
 mod struggle_combinations;
 mod word_speed_tracker;
 
 mod performance_tracker;
 
+pub(crate) use key_stats::{KeyStats, KeyStatsEntry};
+pub(crate) use keystroke_stats::KeystrokeStats;
+pub use mastery::{LearnedSpeed, MasteryPolicy};
 pub(crate) use performance_tracker::PerformanceTracker;
@@ -6,11 +6,18 @@
 //! the end of a session.
 
 use super::fastest_slowest_words::FastestSlowestWords;
+use super::key_stats::{KeyStats, KeyStatsEntry};
+use super::keystroke_stats::KeystrokeStats;
+use super::mastery::MasteryPolicy;
 use super::problem_words::{ProblemWordEntry, ProblemWords};
-use super::struggle_combinations::StruggleCombinations;
+use super::struggle_combinations::{CombinationStats, StruggleCombinations};
 use super::word_speed_tracker::WordSpeedTracker;
 use std::time::{Duration, Instant};
 
+use crate::history::{ProblemWordRecord, WordSpeedRecord};
+use crate::keyboard_layout::KeyboardLayout;
+use crate::report::{CombinationReport, FinalScores, FingerReport, KeyReport, KeystrokeReport};
+
 /// Tracks all performance metrics during a typing session.
 ///
 /// This struct maintains state for:
@@ -25,6 +32,9 @@ pub(crate) struct PerformanceTracker {
     fastest_slowest_words: FastestSlowestWords,
     problem_words: ProblemWords,
     struggle_combinations: StruggleCombinations,
+    keystroke_stats: KeystrokeStats,
+    key_stats: KeyStats,
+    first_keypress_time: Option<Instant>,
     last_keypress_time: Option<Instant>,
     word_start_time: Option<Instant>,
     total_time: Duration,
@@ -40,6 +50,9 @@ impl Default for PerformanceTracker {
             fastest_slowest_words: FastestSlowestWords::default(),
             problem_words: ProblemWords::default(),
             struggle_combinations: StruggleCombinations::default(),
+            keystroke_stats: KeystrokeStats::default(),
+            key_stats: KeyStats::default(),
+            first_keypress_time: None,
             last_keypress_time: None,
             word_start_time: None,
             total_time: Duration::ZERO,
@@ -53,8 +66,10 @@ impl Default for PerformanceTracker {
 impl PerformanceTracker {
     // --- Keypress tracking ---
 
-    pub(crate) fn update_struggle_combinations(&mut self, duration: Duration, user_input: &str) {
-        self.struggle_combinations.update(duration, user_input);
+    /// Time the letter combinations ending with this keystroke.
+    pub(crate) fn update_struggle_combinations(&mut self, key: char, correct: bool, time: Instant) {
+        self.struggle_combinations
+            .record_keystroke(key, correct, time);
     }
 
     pub(crate) fn last_keypress_time(&self) -> Option<Instant> {
@@ -62,9 +77,18 @@ impl PerformanceTracker {
     }
 
     pub(crate) fn set_last_keypress_time(&mut self, time: Instant) {
+        self.first_keypress_time.get_or_insert(time);
         self.last_keypress_time = Some(time);
     }
 
+    /// Time between the first and the latest keypress of the session.
+    pub(crate) fn typing_duration(&self) -> Duration {
+        match (self.first_keypress_time, self.last_keypress_time) {
+            (Some(first), Some(last)) => last.duration_since(first),
+            _ => Duration::ZERO,
+        }
+    }
+
     // --- Word start timing ---
 
     pub(crate) fn start_word_if_needed(&mut self, time: Instant) {
@@ -77,6 +101,39 @@ impl PerformanceTracker {
         self.word_start_time
     }
 
+    // --- Keystroke accuracy ---
+
+    pub(crate) fn record_keystroke(&mut self, correct: bool) {
+        self.keystroke_stats.record_keystroke(correct);
+    }
+
+    pub(crate) fn record_corrected_error(&mut self) {
+        self.keystroke_stats.record_corrected_error();
+    }
+
+    pub(crate) fn record_submitted_word(&mut self, input: &str, expected: &str) {
+        self.keystroke_stats.record_submitted_word(input, expected);
+    }
+
+    pub(crate) fn keystroke_stats(&self) -> KeystrokeStats {
+        self.keystroke_stats
+    }
+
+    // --- Per-key statistics ---
+
+    pub(crate) fn record_key_press(
+        &mut self,
+        expected: char,
+        correct: bool,
+        latency: Option<Duration>,
+    ) {
+        self.key_stats.record(expected, correct, latency);
+    }
+
+    pub(crate) fn key_stats(&self) -> &KeyStats {
+        &self.key_stats
+    }
+
     // --- Mistyped characters ---
 
     pub(crate) fn record_mistype(&mut self, pos: usize) {
@@ -98,6 +155,7 @@ impl PerformanceTracker {
     // --- Backspace ---
 
     pub(crate) fn record_backspace(&mut self) {
+        self.struggle_combinations.break_sequence();
         self.backspace_count += 1;
     }
 
@@ -107,14 +165,19 @@ impl PerformanceTracker {
 
     // --- Word completion ---
 
-    pub(crate) fn record_word_completed(&mut self, word_len: u32) {
+    pub(crate) fn record_word_completed(&mut self, word_len: u32, now: Instant) {
         if let Some(start_time) = self.word_start_time {
-            self.total_time += start_time.elapsed();
+            self.total_time += now.saturating_duration_since(start_time);
             self.total_correct_chars += word_len;
         }
     }
 
+    pub(crate) fn has_completed_words(&self) -> bool {
+        self.total_correct_chars > 0
+    }
+
     pub(crate) fn reset_word_state(&mut self) {
+        self.struggle_combinations.break_sequence();
         self.mistyped_chars.clear();
         self.backspace_count = 0;
         self.word_start_time = None;
@@ -130,6 +193,19 @@ impl PerformanceTracker {
         self.word_speed_tracker.average_speed_last_10_words()
     }
 
+    /// Add the speed of a word completed at `now` to the session timeline.
+    pub(crate) fn record_speed_timeline(&mut self, speed: f32, now: Instant) {
+        let at = self
+            .first_keypress_time
+            .map_or(Duration::ZERO, |first| now.saturating_duration_since(first));
+        self.word_speed_tracker.record_timeline(at, speed);
+    }
+
+    /// Speed of every completed word, at its offset from the first keystroke.
+    pub(crate) fn speed_timeline(&self) -> &[(Duration, f32)] {
+        self.word_speed_tracker.timeline()
+    }
+
     pub(crate) fn update_fastest_slowest_words(&mut self, word: &str, speed: f32) {
         self.fastest_slowest_words.update(word, speed);
     }
@@ -140,12 +216,15 @@ impl PerformanceTracker {
         self.problem_words.add(word, speed, self.backspace_count);
     }
 
-    pub(crate) fn update_problem_word_correct_attempts(&mut self, word: &str) {
-        self.problem_words.update_correct_attempts(word);
+    pub(crate) fn update_problem_word_correct_attempts(&mut self, word: &str, speed: f32) {
+        self.problem_words.update_correct_attempts(word, speed);
     }
 
-    pub(crate) fn remove_learned_words(&mut self) {
-        self.problem_words.remove_learned_words();
+    /// Drop the problem words mastered under `policy`, relative to the
+    /// average speed of the session.
+    pub(crate) fn remove_learned_words(&mut self, policy: &MasteryPolicy) {
+        self.problem_words
+            .remove_learned_words(policy, self.average_wpm());
     }
 
     // --- Aggregate stats ---
@@ -158,6 +237,18 @@ impl PerformanceTracker {
         (self.total_correct_chars as f32 / 5.0) / minutes
     }
 
+    pub(crate) fn accuracy(&self) -> f32 {
+        self.keystroke_stats.accuracy()
+    }
+
+    pub(crate) fn raw_wpm(&self) -> f32 {
+        self.keystroke_stats.raw_wpm(self.typing_duration())
+    }
+
+    pub(crate) fn net_wpm(&self) -> f32 {
+        self.keystroke_stats.net_wpm(self.typing_duration())
+    }
+
     pub(crate) fn get_fastest_words(&self) -> &[(String, f32)] {
         self.fastest_slowest_words.get_fastest_words()
     }
@@ -170,42 +261,80 @@ impl PerformanceTracker {
         self.problem_words.get_words()
     }
 
-    pub(crate) fn get_struggle_combinations(&self) -> &[(String, f32)] {
+    pub(crate) fn get_struggle_combinations(&self) -> Vec<CombinationStats> {
         self.struggle_combinations.get_combinations()
     }
 
-    pub(crate) fn generate_final_scores(&self) -> String {
-        let json = serde_json::json!({
-            "average_speed": self.average_wpm(),
-            "problem_words": self.get_problem_words().iter().map(|e| {
-                serde_json::json!({
-                    "word": e.word,
-                    "speed": e.avg_speed,
-                    "backspaces": e.backspaces,
-                    "correct_attempts": e.correct_attempts
+    /// The final session statistics, with finger statistics computed for
+    /// `layout`.
+    pub(crate) fn final_scores(&self, layout: &KeyboardLayout) -> FinalScores {
+        let keystroke_report = |entry: &KeyStatsEntry| KeystrokeReport {
+            hits: entry.hits,
+            misses: entry.misses,
+            error_rate: entry.error_rate(),
+            mean_latency_ms: entry.mean_latency().map(|d| d.as_secs_f64() * 1000.0),
+            p90_latency_ms: entry.p90_latency().map(|d| d.as_secs_f64() * 1000.0),
+        };
+        let word_speeds = |words: &[(String, f32)]| {
+            words
+                .iter()
+                .map(|(word, speed)| WordSpeedRecord {
+                    word: word.clone(),
+                    speed: *speed,
+                })
+                .collect()
+        };
+        FinalScores {
+            average_speed: self.average_wpm(),
+            accuracy: self.accuracy(),
+            raw_wpm: self.raw_wpm(),
+            net_wpm: self.net_wpm(),
+            words_typed: self.keystroke_stats.words_typed,
+            total_keystrokes: self.keystroke_stats.total_keystrokes,
+            correct_keystrokes: self.keystroke_stats.correct_keystrokes,
+            corrected_errors: self.keystroke_stats.corrected_errors,
+            uncorrected_errors: self.keystroke_stats.uncorrected_errors,
+            problem_words: self
+                .get_problem_words()
+                .iter()
+                .map(|e| ProblemWordRecord {
+                    word: e.word.clone(),
+                    speed: e.avg_speed,
+                    backspaces: e.backspaces,
+                    correct_attempts: e.correct_attempts,
                 })
-            }).collect::<Vec<_>>(),
-            "fastest_words": self.get_fastest_words().iter().map(|(word, speed)| {
-                serde_json::json!({
-                    "word": word,
-                    "speed": speed
+                .collect(),
+            fastest_words: word_speeds(self.get_fastest_words()),
+            slowest_words: word_speeds(self.get_slowest_words()),
+            struggle_combinations: self
+                .get_struggle_combinations()
+                .into_iter()
+                .map(|stats| CombinationReport {
+                    speed: stats.speed(),
+                    mean_latency_ms: stats.mean_latency.as_secs_f64() * 1000.0,
+                    samples: stats.samples,
+                    combination: stats.combination,
                 })
-            }).collect::<Vec<_>>(),
-            "slowest_words": self.get_slowest_words().iter().map(|(word, speed)| {
-                serde_json::json!({
-                    "word": word,
-                    "speed": speed
+                .collect(),
+            key_stats: self
+                .key_stats()
+                .iter()
+                .map(|(key, entry)| KeyReport {
+                    key: key.to_string(),
+                    stats: keystroke_report(entry),
                 })
-            }).collect::<Vec<_>>(),
-            "struggle_combinations": self.get_struggle_combinations().iter().map(|(combo, speed)| {
-                serde_json::json!({
-                    "combination": combo,
-                    "speed": speed
+                .collect(),
+            layout: layout.name.clone(),
+            finger_stats: self
+                .key_stats()
+                .by_finger(layout)
+                .iter()
+                .map(|(finger, entry)| FingerReport {
+                    finger: finger.short_name().to_string(),
+                    stats: keystroke_report(entry),
                 })
-            }).collect::<Vec<_>>()
-        });
-
-        serde_json::to_string_pretty(&json).unwrap_or_else(|_| "{}".to_string())
+                .collect(),
+        }
     }
 }
 
@@ -252,6 +381,34 @@ mod tests {
         assert_eq!(avg_wpm, 50.0);
     }
 
+    #[test]
+    fn test_typing_duration() {
+        let mut tracker = PerformanceTracker::default();
+        assert_eq!(tracker.typing_duration(), Duration::ZERO);
+
+        let start = Instant::now();
+        tracker.set_last_keypress_time(start);
+        tracker.set_last_keypress_time(start + Duration::from_secs(3));
+        tracker.set_last_keypress_time(start + Duration::from_secs(30));
+        assert_eq!(tracker.typing_duration(), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_raw_and_net_wpm() {
+        let start = Instant::now();
+        let mut tracker = PerformanceTracker::default();
+        tracker.set_last_keypress_time(start);
+        tracker.set_last_keypress_time(start + Duration::from_secs(60));
+        for _ in 0..50 {
+            tracker.record_keystroke(true);
+        }
+        tracker.record_submitted_word("hallo", "hello");
+
+        assert_eq!(tracker.raw_wpm(), 10.0);
+        assert_eq!(tracker.net_wpm(), 9.0);
+        assert_eq!(tracker.accuracy(), 100.0);
+    }
+
     #[test]
     fn test_average_wpm_zero_time() {
         let tracker = PerformanceTracker::default();
@@ -259,14 +416,21 @@ mod tests {
     }
 
     #[test]
-    fn test_generate_final_scores() {
+    fn test_final_scores() {
         let tracker = PerformanceTracker::default();
-        let result = tracker.generate_final_scores();
+        let result =
+            serde_json::to_string(&tracker.final_scores(&KeyboardLayout::default())).unwrap();
 
         assert!(result.contains("average_speed"));
+        assert!(result.contains("accuracy"));
+        assert!(result.contains("raw_wpm"));
+        assert!(result.contains("net_wpm"));
+        assert!(result.contains("words_typed"));
         assert!(result.contains("problem_words"));
         assert!(result.contains("fastest_words"));
         assert!(result.contains("slowest_words"));
         assert!(result.contains("struggle_combinations"));
+        assert!(result.contains("key_stats"));
+        assert!(result.contains("finger_stats"));
     }
 }
@@ -2,21 +2,17 @@
 //!
 //! This module identifies words that users struggle with and manages their practice
 //! through repetition until they are mastered. Words are considered "learned" when
-//! they meet both speed and accuracy thresholds.
+//! they meet both the speed and accuracy thresholds of a [`MasteryPolicy`].
 
-/// Threshold for considering a word "learned" based on typing speed (WPM).
-const LEARNED_WORD_WPM_THRESHOLD: f32 = 30.0;
-
-/// Number of correct attempts required to consider a word "learned".
-const LEARNED_WORD_CORRECT_ATTEMPTS: u8 = 2;
+use super::mastery::MasteryPolicy;
 
 /// Entry representing a problem word with performance statistics.
 ///
 /// Contains:
 /// - The word itself
-/// - Average typing speed in WPM
+/// - Average typing speed in WPM, over failed and correct attempts
 /// - Number of backspaces used when typing this word
-/// - Count of correct attempts
+/// - Count of correct attempts in a row
 pub(crate) struct ProblemWordEntry {
     pub(crate) word: String,
     pub(crate) avg_speed: f32,
@@ -45,17 +41,19 @@ impl ProblemWords {
         }
     }
 
-    pub(crate) fn update_correct_attempts(&mut self, word: &str) {
+    /// Count a correct attempt at `word`, typed at `speed`.
+    pub(crate) fn update_correct_attempts(&mut self, word: &str, speed: f32) {
         if let Some(entry) = self.words.iter_mut().find(|e| e.word == word) {
-            entry.correct_attempts += 1;
+            entry.avg_speed = (entry.avg_speed + speed) / 2.0;
+            entry.correct_attempts = entry.correct_attempts.saturating_add(1);
         }
     }
 
-    pub(crate) fn remove_learned_words(&mut self) {
-        self.words.retain(|e| {
-            e.avg_speed < LEARNED_WORD_WPM_THRESHOLD
-                || e.correct_attempts < LEARNED_WORD_CORRECT_ATTEMPTS
-        });
+    /// Drop the words `policy` considers mastered, when the session averages
+    /// `average_wpm`.
+    pub(crate) fn remove_learned_words(&mut self, policy: &MasteryPolicy, average_wpm: f32) {
+        self.words
+            .retain(|entry| !policy.is_mastered(entry, average_wpm));
     }
 
     pub(crate) fn get_words(&self) -> &[ProblemWordEntry] {
@@ -95,10 +93,11 @@ mod tests {
     fn test_update_correct_attempts() {
         let mut problem_words = ProblemWords::default();
         problem_words.add("test".to_string(), 25.0, 2);
-        problem_words.update_correct_attempts("test");
+        problem_words.update_correct_attempts("test", 35.0);
 
         let words = problem_words.get_words();
         assert_eq!(words[0].correct_attempts, 1);
+        assert_eq!(words[0].avg_speed, 30.0);
     }
 
     #[test]
@@ -106,12 +105,30 @@ mod tests {
         let mut problem_words = ProblemWords::default();
         problem_words.add("fast".to_string(), 40.0, 0);
         problem_words.add("slow".to_string(), 20.0, 0);
-        problem_words.update_correct_attempts("fast");
-        problem_words.update_correct_attempts("fast");
+        problem_words.update_correct_attempts("fast", 40.0);
+        problem_words.update_correct_attempts("fast", 40.0);
 
-        problem_words.remove_learned_words();
+        problem_words.remove_learned_words(&MasteryPolicy::default(), 35.0);
         let words = problem_words.get_words();
         assert_eq!(words.len(), 1);
         assert_eq!(words[0].word, "slow");
     }
+
+    #[test]
+    fn test_slow_word_learned_once_typed_fast() {
+        let mut problem_words = ProblemWords::default();
+        problem_words.add("slow".to_string(), 10.0, 1);
+        for _ in 0..3 {
+            problem_words.update_correct_attempts("slow", 50.0);
+        }
+        let strict = MasteryPolicy {
+            streak: 4,
+            ..MasteryPolicy::default()
+        };
+        problem_words.remove_learned_words(&strict, 40.0);
+        assert_eq!(problem_words.get_words().len(), 1);
+
+        problem_words.remove_learned_words(&MasteryPolicy::default(), 40.0);
+        assert!(problem_words.get_words().is_empty());
+    }
 }
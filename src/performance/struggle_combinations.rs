@@ -2,8 +2,60 @@
 //!
 //! This module identifies letter combinations (2-3 characters) that users type slowly,
 //! helping to pinpoint specific finger movements or key sequences that are difficult.
+//!
+//! Each correct keystroke that follows another correct keystroke of the same
+//! word is one sample for the bigram they form, timed from the first key to
+//! the second. When two correct keystrokes precede it, the span from the first
+//! of the three is also a sample for the trigram. A wrong key or a backspace
+//! breaks the sequence, so that no sample includes the time spent on a mistake.
+
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+/// Number of combinations reported, slowest first.
+const MAX_REPORTED_COMBINATIONS: usize = 50;
 
-use std::time::Duration;
+/// Samples needed before a combination is reported.
+const MIN_SAMPLES: u32 = 2;
+
+/// Running mean of the time taken to type one combination.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct RunningMean {
+    samples: u32,
+    total: Duration,
+}
+
+impl RunningMean {
+    fn add(&mut self, duration: Duration) {
+        self.samples += 1;
+        self.total += duration;
+    }
+
+    fn mean(&self) -> Duration {
+        self.total / self.samples.max(1)
+    }
+}
+
+/// Timing of one letter combination over the session.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct CombinationStats {
+    pub(crate) combination: String,
+    /// Mean time from the first key of the combination to the last
+    pub(crate) mean_latency: Duration,
+    pub(crate) samples: u32,
+}
+
+impl CombinationStats {
+    /// Typing speed over the combination's transitions, in WPM.
+    pub(crate) fn speed(&self) -> f32 {
+        let transitions = self.combination.chars().count().saturating_sub(1) as f32;
+        let minutes = self.mean_latency.as_secs_f32() / 60.0;
+        if minutes == 0.0 {
+            return 0.0;
+        }
+        (transitions / 5.0) / minutes
+    }
+}
 
 /// Tracks letter combinations that users struggle with during typing.
 ///
@@ -12,53 +64,72 @@ use std::time::Duration;
 /// finger movements or key transitions that are difficult.
 #[derive(Default)]
 pub(crate) struct StruggleCombinations {
-    combinations: Vec<(String, f32)>,
+    combinations: BTreeMap<String, RunningMean>,
+    /// The last correct keystrokes of the current sequence, oldest first
+    sequence: Vec<(char, Instant)>,
 }
 
 impl StruggleCombinations {
-    pub(crate) fn update(&mut self, duration: Duration, user_input: &str) {
-        let combos = self.get_letter_combinations(user_input);
-        for combo in combos {
-            let speed = self.calculate_combo_speed(&combo, duration);
-            if let Some(index) = self.combinations.iter().position(|(c, _)| c == &combo) {
-                let (_, avg_speed) = &mut self.combinations[index];
-                *avg_speed = (*avg_speed + speed) / 2.0;
-            } else {
-                self.combinations.push((combo, speed));
-            }
+    /// Record a keystroke typed at `time`.
+    ///
+    /// A wrong keystroke is not sampled and ends the current sequence.
+    pub(crate) fn record_keystroke(&mut self, key: char, correct: bool, time: Instant) {
+        if !correct {
+            self.break_sequence();
+            return;
         }
-        self.combinations
-            .sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
-        self.combinations.truncate(50);
-    }
-
-    fn get_letter_combinations(&self, user_input: &str) -> Vec<String> {
-        let chars: Vec<char> = user_input.chars().collect();
-        let mut combos = Vec::new();
-        for window_size in [2, 3] {
-            combos.extend(
-                chars
-                    .windows(window_size)
-                    .map(|w| w.iter().collect::<String>()),
-            );
+        self.sequence.push((key, time));
+        if self.sequence.len() > 3 {
+            self.sequence.remove(0);
+        }
+        for start in 0..self.sequence.len().saturating_sub(1) {
+            let combination: String = self.sequence[start..].iter().map(|(c, _)| c).collect();
+            let span = time.saturating_duration_since(self.sequence[start].1);
+            self.combinations.entry(combination).or_default().add(span);
         }
-        combos
     }
 
-    fn calculate_combo_speed(&self, combo: &str, duration: Duration) -> f32 {
-        let minutes = duration.as_secs_f32() / 60.0;
-        (combo.len() as f32 / 5.0) / minutes
+    /// End the current sequence, after a backspace or at the end of a word.
+    pub(crate) fn break_sequence(&mut self) {
+        self.sequence.clear();
     }
 
-    pub(crate) fn get_combinations(&self) -> &[(String, f32)] {
-        &self.combinations
+    /// The slowest combinations with enough samples, slowest first.
+    pub(crate) fn get_combinations(&self) -> Vec<CombinationStats> {
+        let mut combinations: Vec<CombinationStats> = self
+            .combinations
+            .iter()
+            .filter(|(_, mean)| mean.samples >= MIN_SAMPLES)
+            .map(|(combination, mean)| CombinationStats {
+                combination: combination.clone(),
+                mean_latency: mean.mean(),
+                samples: mean.samples,
+            })
+            .collect();
+        combinations.sort_by(|a, b| a.speed().total_cmp(&b.speed()));
+        combinations.truncate(MAX_REPORTED_COMBINATIONS);
+        combinations
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::time::Duration;
+
+    /// Type `keys` correctly at the given offsets in milliseconds.
+    fn type_keys(tracker: &mut StruggleCombinations, start: Instant, keys: &[(char, u64)]) {
+        for &(key, millis) in keys {
+            tracker.record_keystroke(key, true, start + Duration::from_millis(millis));
+        }
+        tracker.break_sequence();
+    }
+
+    fn stats<'a>(combinations: &'a [CombinationStats], combination: &str) -> &'a CombinationStats {
+        combinations
+            .iter()
+            .find(|stats| stats.combination == combination)
+            .unwrap()
+    }
 
     #[test]
     fn test_new_struggle_combinations() {
@@ -67,43 +138,85 @@ mod tests {
     }
 
     #[test]
-    fn test_get_letter_combinations() {
-        let tracker = StruggleCombinations::default();
-        let combos = tracker.get_letter_combinations("hello");
-        // For "hello": he, el, ll, lo (2-char) + hel, ell, llo (3-char) = 7 combinations
-        assert_eq!(combos.len(), 7);
+    fn test_transitions_are_timed_separately() {
+        let mut tracker = StruggleCombinations::default();
+        let start = Instant::now();
+        for _ in 0..2 {
+            type_keys(&mut tracker, start, &[('t', 0), ('h', 100), ('e', 400)]);
+        }
+
+        let combinations = tracker.get_combinations();
+        assert_eq!(combinations.len(), 3);
+        assert_eq!(
+            stats(&combinations, "th").mean_latency,
+            Duration::from_millis(100)
+        );
+        assert_eq!(
+            stats(&combinations, "he").mean_latency,
+            Duration::from_millis(300)
+        );
+        assert_eq!(
+            stats(&combinations, "the").mean_latency,
+            Duration::from_millis(400)
+        );
+        assert_eq!(stats(&combinations, "the").samples, 2);
+        // Slowest first: "he" at 40 WPM, then "the" at 60 and "th" at 120.
+        assert_eq!(combinations[0].combination, "he");
+        assert_eq!(combinations[2].combination, "th");
     }
 
     #[test]
-    fn test_update_combinations() {
+    fn test_running_mean() {
         let mut tracker = StruggleCombinations::default();
-        let duration = Duration::from_secs(1);
+        let start = Instant::now();
+        for millis in [100, 200, 600] {
+            type_keys(&mut tracker, start, &[('a', 0), ('o', millis)]);
+        }
 
-        tracker.update(duration, "test");
-        let combos = tracker.get_combinations();
-        assert!(!combos.is_empty());
+        let combinations = tracker.get_combinations();
+        let ao = stats(&combinations, "ao");
+        assert_eq!(ao.samples, 3);
+        assert_eq!(ao.mean_latency, Duration::from_millis(300));
+        assert!((ao.speed() - 40.0).abs() < 1e-3);
     }
 
     #[test]
-    fn test_calculate_combo_speed() {
-        let tracker = StruggleCombinations::default();
-        let duration = Duration::from_secs(60); // 1 minute
-        let speed = tracker.calculate_combo_speed("ab", duration);
-        // (2 chars / 5) / 1 minute = 0.4 WPM
-        assert_eq!(speed, 0.4);
+    fn test_errors_break_the_sequence() {
+        let mut tracker = StruggleCombinations::default();
+        let start = Instant::now();
+        for _ in 0..2 {
+            tracker.record_keystroke('t', true, start);
+            tracker.record_keystroke('x', false, start + Duration::from_millis(100));
+            tracker.break_sequence();
+            tracker.record_keystroke('h', true, start + Duration::from_millis(900));
+            tracker.record_keystroke('e', true, start + Duration::from_millis(1000));
+            tracker.break_sequence();
+        }
+
+        let combinations = tracker.get_combinations();
+        assert_eq!(combinations.len(), 1);
+        assert_eq!(combinations[0].combination, "he");
     }
 
     #[test]
-    fn test_combinations_limit() {
+    fn test_single_samples_are_not_reported() {
         let mut tracker = StruggleCombinations::default();
-        let duration = Duration::from_secs(1);
+        type_keys(&mut tracker, Instant::now(), &[('t', 0), ('h', 100)]);
+        assert!(tracker.get_combinations().is_empty());
+    }
 
-        // Add many combinations
-        for i in 0..60 {
-            tracker.update(duration, &format!("word{}", i));
+    #[test]
+    fn test_combinations_limit() {
+        let mut tracker = StruggleCombinations::default();
+        let start = Instant::now();
+        for first in 'a'..='c' {
+            for second in 'a'..='z' {
+                for _ in 0..2 {
+                    type_keys(&mut tracker, start, &[(first, 0), (second, 100)]);
+                }
+            }
         }
 
-        let combos = tracker.get_combinations();
-        assert_eq!(combos.len(), 50); // Should be limited to 50
+        assert_eq!(tracker.get_combinations().len(), MAX_REPORTED_COMBINATIONS);
     }
 }
@@ -1,9 +1,11 @@
 //! Tracking of recent word typing speeds.
 //!
 //! This module maintains a rolling window of the most recent 10 word speeds,
-//! allowing for real-time WPM calculation and display during typing practice.
+//! allowing for real-time WPM calculation and display during typing practice,
+//! and the timeline of every word speed over the session.
 
 use std::collections::VecDeque;
+use std::time::Duration;
 
 /// Tracks the speed (WPM) of recently typed words for real-time statistics.
 ///
@@ -12,6 +14,8 @@ use std::collections::VecDeque;
 #[derive(Default)]
 pub(crate) struct WordSpeedTracker {
     recent_word_speeds: VecDeque<f32>,
+    /// Speed of every completed word, at its offset from the first keystroke
+    timeline: Vec<(Duration, f32)>,
 }
 
 impl WordSpeedTracker {
@@ -29,6 +33,14 @@ impl WordSpeedTracker {
             self.recent_word_speeds.iter().sum::<f32>() / self.recent_word_speeds.len() as f32
         }
     }
+
+    pub(crate) fn record_timeline(&mut self, at: Duration, speed: f32) {
+        self.timeline.push((at, speed));
+    }
+
+    pub(crate) fn timeline(&self) -> &[(Duration, f32)] {
+        &self.timeline
+    }
 }
 
 #[cfg(test)]
@@ -69,4 +81,14 @@ mod tests {
         let expected_avg = (5.0 + 6.0 + 7.0 + 8.0 + 9.0 + 10.0 + 11.0 + 12.0 + 13.0 + 14.0) / 10.0;
         assert_eq!(tracker.average_speed_last_10_words(), expected_avg);
     }
+
+    #[test]
+    fn test_timeline() {
+        let mut tracker = WordSpeedTracker::default();
+        for i in 0..15 {
+            tracker.record_timeline(Duration::from_secs(i), i as f32);
+        }
+        assert_eq!(tracker.timeline().len(), 15);
+        assert_eq!(tracker.timeline()[14], (Duration::from_secs(14), 14.0));
+    }
 }
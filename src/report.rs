@@ -0,0 +1,396 @@
+//! Machine-readable session reports and history exports.
+//!
+//! The statistics of the sessions of a run are written as a [`ReportDocument`],
+//! a JSON document tagged with [`REPORT_VERSION`]. The version is bumped
+//! whenever a field is renamed, removed or changes meaning; new fields may be
+//! added without a bump. Past sessions are exported from the history as JSON in
+//! the schema of the history file, or as CSV or Markdown tables.
+//!
+//! The two JSON documents are versioned separately: an export is tagged with
+//! the history [`SCHEMA_VERSION`](history::SCHEMA_VERSION) rather than
+//! [`REPORT_VERSION`], since the history keeps only a summary of each session,
+//! without the key, finger and ghost race statistics of a report.
+
+use std::fmt::Write as _;
+
+use anyhow::Result;
+
+use jiff::{Timestamp, tz::TimeZone};
+use serde::{Deserialize, Serialize};
+
+use crate::history::{self, ProblemWordRecord, SessionRecord, WordSpeedRecord};
+
+/// Schema version of the session reports written by this release.
+pub(crate) const REPORT_VERSION: u32 = 1;
+
+/// The reports of the sessions of one run, in the order they were played.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct ReportDocument {
+    pub(crate) version: u32,
+    pub(crate) sessions: Vec<SessionReport>,
+}
+
+impl ReportDocument {
+    pub(crate) fn new(sessions: Vec<SessionReport>) -> Self {
+        ReportDocument {
+            version: REPORT_VERSION,
+            sessions,
+        }
+    }
+}
+
+/// The statistics of one finished session.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct SessionReport {
+    /// When the session ended
+    pub(crate) timestamp: Timestamp,
+    /// Word list level, counted from 1
+    pub(crate) level: usize,
+    pub(crate) level_name: String,
+    /// Seed of the word order
+    pub(crate) seed: Option<u64>,
+    #[serde(flatten)]
+    pub(crate) scores: FinalScores,
+    /// Comparison with the ghost, `null` unless racing one
+    pub(crate) ghost_race: Option<GhostRaceReport>,
+}
+
+/// The typing statistics of a session, as measured by the performance tracker.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct FinalScores {
+    /// Mean speed of the completed words, in WPM
+    pub(crate) average_speed: f32,
+    /// Percentage of correct keystrokes
+    pub(crate) accuracy: f32,
+    pub(crate) raw_wpm: f32,
+    pub(crate) net_wpm: f32,
+    pub(crate) words_typed: u32,
+    pub(crate) total_keystrokes: u32,
+    pub(crate) correct_keystrokes: u32,
+    pub(crate) corrected_errors: u32,
+    pub(crate) uncorrected_errors: u32,
+    pub(crate) problem_words: Vec<ProblemWordRecord>,
+    pub(crate) fastest_words: Vec<WordSpeedRecord>,
+    pub(crate) slowest_words: Vec<WordSpeedRecord>,
+    /// Slowest combinations first
+    pub(crate) struggle_combinations: Vec<CombinationReport>,
+    /// Statistics of every expected key, in key order
+    pub(crate) key_stats: Vec<KeyReport>,
+    /// Name of the keyboard layout
+    pub(crate) layout: String,
+    /// Statistics of the keys typed by each finger of the layout
+    pub(crate) finger_stats: Vec<FingerReport>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct CombinationReport {
+    pub(crate) combination: String,
+    /// Speed over the transitions of the combination, in WPM
+    pub(crate) speed: f32,
+    pub(crate) mean_latency_ms: f64,
+    /// Error-free occurrences timed
+    pub(crate) samples: u32,
+}
+
+/// Hits, misses and latency of a group of keystrokes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct KeystrokeReport {
+    pub(crate) hits: u32,
+    pub(crate) misses: u32,
+    /// Misses over attempts, from 0 to 1
+    pub(crate) error_rate: f32,
+    /// `null` until a latency was measured
+    pub(crate) mean_latency_ms: Option<f64>,
+    pub(crate) p90_latency_ms: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct KeyReport {
+    pub(crate) key: String,
+    #[serde(flatten)]
+    pub(crate) stats: KeystrokeReport,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct FingerReport {
+    /// Short finger name, such as `LI` for the left index
+    pub(crate) finger: String,
+    #[serde(flatten)]
+    pub(crate) stats: KeystrokeReport,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct GhostRaceReport {
+    pub(crate) won: bool,
+    /// Words compared, those both sides typed correctly
+    pub(crate) words: usize,
+    pub(crate) wpm: f32,
+    pub(crate) ghost_wpm: f32,
+    pub(crate) wpm_delta: f32,
+    /// Seconds the player was ahead of the ghost, negative when behind
+    pub(crate) time_delta_secs: f32,
+}
+
+/// Output formats of `dvoratt export`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "tui", derive(clap::ValueEnum))]
+pub(crate) enum ExportFormat {
+    /// The versioned schema of the history file
+    Json,
+    /// One row per session, for spreadsheets
+    Csv,
+    /// A Markdown table, for wiki pages
+    #[cfg_attr(feature = "tui", value(name = "md"))]
+    Markdown,
+}
+
+/// Export `sessions` in `format`, with Markdown dates in the time zone `tz`.
+pub(crate) fn export_sessions(
+    sessions: &[SessionRecord],
+    format: ExportFormat,
+    tz: &TimeZone,
+) -> Result<String> {
+    Ok(match format {
+        ExportFormat::Json => export_json(sessions)?,
+        ExportFormat::Csv => export_csv(sessions),
+        ExportFormat::Markdown => export_markdown(sessions, tz),
+    })
+}
+
+/// The sessions in a document tagged with the history schema version.
+fn export_json(sessions: &[SessionRecord]) -> Result<String> {
+    #[derive(Serialize)]
+    struct Export<'a> {
+        version: u32,
+        sessions: &'a [SessionRecord],
+    }
+    let export = Export {
+        version: history::SCHEMA_VERSION,
+        sessions,
+    };
+    Ok(serde_json::to_string_pretty(&export)? + "\n")
+}
+
+const CSV_HEADER: &str = "timestamp,level,level_name,average_wpm,net_wpm,raw_wpm,accuracy,\
+words_typed,corrected_errors,uncorrected_errors,problem_words,seed";
+
+fn export_csv(sessions: &[SessionRecord]) -> String {
+    let mut csv = format!("{CSV_HEADER}\n");
+    for session in sessions {
+        let keystrokes = &session.keystrokes;
+        let fields = [
+            session.timestamp.to_string(),
+            session.level.to_string(),
+            csv_field(&session.level_name),
+            format!("{:.2}", session.average_wpm),
+            format!("{:.2}", keystrokes.net_wpm),
+            format!("{:.2}", keystrokes.raw_wpm),
+            format!("{:.2}", keystrokes.accuracy),
+            keystrokes.words_typed.to_string(),
+            keystrokes.corrected_errors.to_string(),
+            keystrokes.uncorrected_errors.to_string(),
+            csv_field(&problem_words(session)),
+            optional(session.seed.map(|seed| seed.to_string())),
+        ];
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+/// Quote a CSV field if it contains a separator, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn export_markdown(sessions: &[SessionRecord], tz: &TimeZone) -> String {
+    let mut table = String::from(
+        "| Date | Level | WPM | Net WPM | Accuracy | Words | Errors | Problem words |\n\
+         |------|-------|----:|--------:|---------:|------:|-------:|---------------|\n",
+    );
+    for session in sessions {
+        let keystrokes = &session.keystrokes;
+        let _ = writeln!(
+            table,
+            "| {} | {} {} | {:.2} | {:.2} | {:.1}% | {} | {} | {} |",
+            session
+                .timestamp
+                .to_zoned(tz.clone())
+                .strftime("%Y-%m-%d %H:%M"),
+            session.level,
+            markdown_cell(&session.level_name),
+            session.average_wpm,
+            keystrokes.net_wpm,
+            keystrokes.accuracy,
+            keystrokes.words_typed,
+            keystrokes.corrected_errors + keystrokes.uncorrected_errors,
+            markdown_cell(&problem_words(session)),
+        );
+    }
+    table
+}
+
+/// Escape the characters that would end a Markdown table cell.
+fn markdown_cell(value: &str) -> String {
+    value.replace('|', "\\|").replace('\n', " ")
+}
+
+fn problem_words(session: &SessionRecord) -> String {
+    session
+        .problem_words
+        .iter()
+        .map(|problem| problem.word.as_str())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// An empty cell for values a session does not have.
+fn optional(value: Option<String>) -> String {
+    value.unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::KeystrokeRecord;
+
+    fn session(level_name: &str) -> SessionRecord {
+        SessionRecord {
+            timestamp: "2026-09-01T10:00:00Z".parse().unwrap(),
+            level: 2,
+            level_name: level_name.to_string(),
+            adaptive: false,
+            average_wpm: 41.5,
+            keystrokes: KeystrokeRecord {
+                accuracy: 96.0,
+                raw_wpm: 45.0,
+                net_wpm: 40.25,
+                words_typed: 30,
+                total_keystrokes: 150,
+                correct_keystrokes: 144,
+                corrected_errors: 4,
+                uncorrected_errors: 2,
+            },
+            problem_words: vec![
+                ProblemWordRecord {
+                    word: "queue".to_string(),
+                    speed: 12.0,
+                    backspaces: 2,
+                    correct_attempts: 0,
+                },
+                ProblemWordRecord {
+                    word: "thud".to_string(),
+                    speed: 15.0,
+                    backspaces: 1,
+                    correct_attempts: 1,
+                },
+            ],
+            struggle_combinations: Vec::new(),
+            fastest_words: Vec::new(),
+            slowest_words: Vec::new(),
+            log_file: None,
+            seed: Some(7),
+        }
+    }
+
+    #[test]
+    fn test_export_json() {
+        let sessions = [session("Home Row")];
+        let json = export_sessions(&sessions, ExportFormat::Json, &TimeZone::UTC).unwrap();
+        let document: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(document["version"], history::SCHEMA_VERSION);
+        let exported: Vec<SessionRecord> =
+            serde_json::from_value(document["sessions"].clone()).unwrap();
+        assert_eq!(exported, sessions);
+    }
+
+    #[test]
+    fn test_export_csv() {
+        let mut unseeded = session("Home Row, \"easy\"");
+        unseeded.seed = None;
+        let csv = export_sessions(
+            &[session("Home Row"), unseeded],
+            ExportFormat::Csv,
+            &TimeZone::UTC,
+        )
+        .unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], CSV_HEADER);
+        assert_eq!(
+            lines[1],
+            "2026-09-01T10:00:00Z,2,Home Row,41.50,40.25,45.00,96.00,30,4,2,queue thud,7"
+        );
+        assert_eq!(
+            lines[2],
+            "2026-09-01T10:00:00Z,2,\"Home Row, \"\"easy\"\"\",41.50,40.25,45.00,96.00,30,4,2,queue thud,"
+        );
+    }
+
+    #[test]
+    fn test_export_markdown() {
+        let md = export_sessions(
+            &[session("Keys a|o")],
+            ExportFormat::Markdown,
+            &TimeZone::UTC,
+        )
+        .unwrap();
+        let lines: Vec<&str> = md.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[2],
+            "| 2026-09-01 10:00 | 2 Keys a\\|o | 41.50 | 40.25 | 96.0% | 30 | 6 | queue thud |"
+        );
+    }
+
+    #[test]
+    fn test_report_document_roundtrip() {
+        let report = SessionReport {
+            timestamp: Timestamp::UNIX_EPOCH,
+            level: 1,
+            level_name: "Home Row".to_string(),
+            seed: Some(3),
+            scores: FinalScores {
+                average_speed: 30.0,
+                accuracy: 100.0,
+                raw_wpm: 30.0,
+                net_wpm: 30.0,
+                words_typed: 1,
+                total_keystrokes: 4,
+                correct_keystrokes: 4,
+                corrected_errors: 0,
+                uncorrected_errors: 0,
+                problem_words: Vec::new(),
+                fastest_words: Vec::new(),
+                slowest_words: Vec::new(),
+                struggle_combinations: Vec::new(),
+                key_stats: vec![KeyReport {
+                    key: "a".to_string(),
+                    stats: KeystrokeReport {
+                        hits: 1,
+                        misses: 0,
+                        error_rate: 0.0,
+                        mean_latency_ms: None,
+                        p90_latency_ms: None,
+                    },
+                }],
+                layout: "Dvorak".to_string(),
+                finger_stats: Vec::new(),
+            },
+            ghost_race: None,
+        };
+        let document = ReportDocument::new(vec![report]);
+        let json = serde_json::to_value(&document).unwrap();
+        assert_eq!(json["version"], REPORT_VERSION);
+        assert_eq!(json["sessions"][0]["average_speed"], 30.0);
+        assert_eq!(json["sessions"][0]["key_stats"][0]["hits"], 1);
+        assert!(json["sessions"][0]["ghost_race"].is_null());
+        assert_eq!(
+            serde_json::from_value::<ReportDocument>(json).unwrap(),
+            document
+        );
+    }
+}
@@ -0,0 +1,267 @@
+//! Spaced repetition of problem words across sessions.
+//!
+//! Every word that becomes a problem word joins a review deck scheduled with
+//! the SM-2 algorithm: each review grades the word, which updates its ease
+//! factor and the number of days until it is due again. Words due at the start
+//! of a session are mixed into its word queue.
+//!
+//! The deck is stored as a versioned JSON document next to the session history
+//! (`$XDG_DATA_HOME/dvoratt/reviews.json` on Linux).
+
+use anyhow::{Context, Result, bail};
+use jiff::{SignedDuration, Timestamp};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Schema version written by this release.
+pub(crate) const SCHEMA_VERSION: u32 = 1;
+
+/// Ease factor of a word entering the deck.
+const INITIAL_EASE: f32 = 2.5;
+
+/// SM-2 never lets the ease factor drop below this.
+const MIN_EASE: f32 = 1.3;
+
+/// How well a word was typed when it was reviewed, worst first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Grade {
+    /// Mistyped or corrected with backspace
+    Again,
+    /// Typed correctly but slowly
+    Hard,
+    /// Typed correctly at a good speed
+    Good,
+}
+
+impl Grade {
+    /// The SM-2 response quality, from 0 to 5.
+    fn quality(self) -> f32 {
+        match self {
+            Grade::Again => 1.0,
+            Grade::Hard => 3.0,
+            Grade::Good => 4.0,
+        }
+    }
+}
+
+/// Scheduling state of one word.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct ReviewItem {
+    pub(crate) word: String,
+    /// SM-2 ease factor, multiplying the interval after each successful review
+    pub(crate) ease: f32,
+    /// Days between the last review and the next one
+    pub(crate) interval_days: u32,
+    /// Successful reviews in a row
+    pub(crate) repetitions: u32,
+    pub(crate) due: Timestamp,
+}
+
+impl ReviewItem {
+    fn new(word: String, now: Timestamp) -> Self {
+        ReviewItem {
+            word,
+            ease: INITIAL_EASE,
+            interval_days: 0,
+            repetitions: 0,
+            due: now,
+        }
+    }
+
+    /// Apply one SM-2 review at `now`.
+    fn review(&mut self, grade: Grade, now: Timestamp) {
+        let quality = grade.quality();
+        if grade == Grade::Again {
+            self.repetitions = 0;
+            self.interval_days = 1;
+        } else {
+            self.repetitions += 1;
+            self.interval_days = match self.repetitions {
+                1 => 1,
+                2 => 6,
+                _ => (self.interval_days as f32 * self.ease).round() as u32,
+            };
+        }
+        let penalty = 5.0 - quality;
+        self.ease = (self.ease + 0.1 - penalty * (0.08 + penalty * 0.02)).max(MIN_EASE);
+        self.due = now + SignedDuration::from_hours(24 * i64::from(self.interval_days));
+    }
+}
+
+/// Every word under review.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct ReviewDeck {
+    pub(crate) items: Vec<ReviewItem>,
+}
+
+impl ReviewDeck {
+    /// Words due at `now`, the most overdue first.
+    pub(crate) fn due_words(&self, now: Timestamp) -> Vec<String> {
+        let mut due: Vec<&ReviewItem> = self.items.iter().filter(|item| item.due <= now).collect();
+        due.sort_by_key(|item| item.due);
+        due.into_iter().map(|item| item.word.clone()).collect()
+    }
+
+    /// Record a review of `word`, adding it to the deck if it is new.
+    pub(crate) fn review(&mut self, word: &str, grade: Grade, now: Timestamp) {
+        let index = match self.items.iter().position(|item| item.word == word) {
+            Some(index) => index,
+            None => {
+                self.items.push(ReviewItem::new(word.to_string(), now));
+                self.items.len() - 1
+            }
+        };
+        self.items[index].review(grade, now);
+    }
+}
+
+/// On-disk representation of the deck, tagged with its schema version.
+#[derive(Serialize)]
+struct Document<'a> {
+    version: u32,
+    #[serde(flatten)]
+    deck: &'a ReviewDeck,
+}
+
+/// Reads and writes the review deck file.
+pub(crate) struct ReviewStore {
+    path: PathBuf,
+}
+
+impl ReviewStore {
+    pub(crate) fn new(path: impl Into<PathBuf>) -> Self {
+        ReviewStore { path: path.into() }
+    }
+
+    /// Open the store at its default location in the user data directory.
+    pub(crate) fn open_default() -> Result<Self> {
+        let data_dir = dirs::data_dir().context("could not determine the user data directory")?;
+        Ok(Self::new(data_dir.join("dvoratt").join("reviews.json")))
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Load the deck. A missing file is an empty deck.
+    pub(crate) fn load(&self) -> Result<ReviewDeck> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(ReviewDeck::default());
+            }
+            Err(err) => {
+                return Err(err).with_context(|| format!("failed to read {}", self.path.display()));
+            }
+        };
+        let document: Value = serde_json::from_str(&contents)
+            .with_context(|| format!("{} is not valid JSON", self.path.display()))?;
+        let version = document.get("version").and_then(Value::as_u64);
+        if version != Some(u64::from(SCHEMA_VERSION)) {
+            bail!(
+                "{} has an unsupported schema version, this release reads version {SCHEMA_VERSION}",
+                self.path.display()
+            );
+        }
+        serde_json::from_value(document)
+            .with_context(|| format!("{} is not a valid review file", self.path.display()))
+    }
+
+    /// Write the whole deck, replacing the file atomically.
+    pub(crate) fn save(&self, deck: &ReviewDeck) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create {}", parent.display()))?;
+        }
+        let document = Document {
+            version: SCHEMA_VERSION,
+            deck,
+        };
+        let contents = serde_json::to_string_pretty(&document)?;
+        let tmp_path = self.path.with_extension("json.tmp");
+        fs::write(&tmp_path, contents)
+            .with_context(|| format!("failed to write {}", tmp_path.display()))?;
+        fs::rename(&tmp_path, &self.path)
+            .with_context(|| format!("failed to replace {}", self.path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn days(n: i64) -> SignedDuration {
+        SignedDuration::from_hours(24 * n)
+    }
+
+    #[test]
+    fn test_sm2_intervals() {
+        let now = Timestamp::UNIX_EPOCH;
+        let mut deck = ReviewDeck::default();
+        deck.review("heed", Grade::Again, now);
+        let item = &deck.items[0];
+        assert_eq!((item.interval_days, item.repetitions), (1, 0));
+        assert!((item.ease - 1.96).abs() < 1e-5);
+        assert_eq!(item.due, now + days(1));
+
+        deck.review("heed", Grade::Good, now);
+        deck.review("heed", Grade::Good, now);
+        assert_eq!(deck.items[0].interval_days, 6);
+        deck.review("heed", Grade::Good, now);
+        assert_eq!(deck.items[0].interval_days, 12);
+
+        deck.review("heed", Grade::Again, now);
+        assert_eq!(deck.items[0].interval_days, 1);
+        assert_eq!(deck.items.len(), 1);
+    }
+
+    #[test]
+    fn test_hard_lowers_ease_down_to_minimum() {
+        let now = Timestamp::UNIX_EPOCH;
+        let mut deck = ReviewDeck::default();
+        for _ in 0..20 {
+            deck.review("thud", Grade::Hard, now);
+        }
+        assert_eq!(deck.items[0].ease, MIN_EASE);
+    }
+
+    #[test]
+    fn test_due_words() {
+        let now = Timestamp::UNIX_EPOCH;
+        let mut deck = ReviewDeck::default();
+        deck.review("late", Grade::Again, now);
+        deck.review("later", Grade::Good, now);
+        deck.review("early", Grade::Again, now - days(3));
+
+        assert!(deck.due_words(now).contains(&"early".to_string()));
+        assert_eq!(deck.due_words(now + days(1)), ["early", "late", "later"]);
+        assert_eq!(deck.due_words(now - days(3)), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ReviewStore::new(dir.path().join("nested").join("reviews.json"));
+        assert_eq!(store.load().unwrap(), ReviewDeck::default());
+
+        let mut deck = ReviewDeck::default();
+        deck.review("heed", Grade::Again, Timestamp::UNIX_EPOCH);
+        store.save(&deck).unwrap();
+        assert_eq!(store.load().unwrap(), deck);
+
+        let raw: Value = serde_json::from_str(&fs::read_to_string(store.path()).unwrap()).unwrap();
+        assert_eq!(raw["version"], SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn test_unsupported_version_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = ReviewStore::new(dir.path().join("reviews.json"));
+        fs::write(store.path(), r#"{"version": 2, "items": []}"#).unwrap();
+        assert!(store.load().is_err());
+        fs::write(store.path(), r#"{"items": []}"#).unwrap();
+        assert!(store.load().is_err());
+    }
+}
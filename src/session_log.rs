@@ -0,0 +1,351 @@
+//! Keystroke logs of practice sessions, for replay.
+//!
+//! A log is a JSON Lines file. The first line is a header with the options
+//! the session started from, so that replaying it presents the same words, and
+//! every following line is one input event. Events carry the monotonic time
+//! since the session started, the word expected when the key was pressed and
+//! the input once it was handled.
+//!
+//! Every practice session is logged to the `dvoratt/sessions` directory of the
+//! user data directory (`$XDG_DATA_HOME/dvoratt/sessions` on Linux).
+
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, bail};
+use jiff::Timestamp;
+use serde::{Deserialize, Serialize};
+
+use crate::app::{App, AppOptions};
+use crate::input::InputEvent;
+use crate::performance::MasteryPolicy;
+
+/// Log format version written by this release.
+pub(crate) const LOG_VERSION: u32 = 1;
+
+/// First line of a log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct LogHeader {
+    pub(crate) version: u32,
+    pub(crate) started_at: Timestamp,
+    /// Options the session was created with
+    pub(crate) options: AppOptions,
+}
+
+/// One input event.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct LogEvent {
+    /// Monotonic time since the session started, in microseconds
+    pub(crate) at_us: u64,
+    pub(crate) event: InputEvent,
+    /// The word to type when the key was pressed
+    pub(crate) expected: String,
+    /// Position of that word among the new words of the session
+    pub(crate) word: usize,
+    /// The input after the key was handled
+    pub(crate) input: String,
+    /// Mastery policy reloaded from the config file before the key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) mastery: Option<MasteryPolicy>,
+}
+
+impl LogEvent {
+    pub(crate) fn at(&self) -> Duration {
+        Duration::from_micros(self.at_us)
+    }
+}
+
+/// A complete log, as read back from a file.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SessionLog {
+    pub(crate) header: LogHeader,
+    pub(crate) events: Vec<LogEvent>,
+}
+
+impl SessionLog {
+    pub(crate) fn load(path: &Path) -> Result<Self> {
+        let file =
+            File::open(path).with_context(|| format!("failed to read {}", path.display()))?;
+        let mut lines = BufReader::new(file).lines();
+        let header_line = lines
+            .next()
+            .transpose()?
+            .with_context(|| format!("{} is empty", path.display()))?;
+        let header: LogHeader = serde_json::from_str(&header_line)
+            .with_context(|| format!("{} has no valid log header", path.display()))?;
+        if header.version != LOG_VERSION {
+            bail!(
+                "{} has log version {}, this release reads version {LOG_VERSION}",
+                path.display(),
+                header.version
+            );
+        }
+
+        let mut events = Vec::new();
+        for (index, line) in lines.enumerate() {
+            let line = line.with_context(|| format!("failed to read {}", path.display()))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let event = serde_json::from_str(&line).with_context(|| {
+                format!("invalid event on line {} of {}", index + 2, path.display())
+            })?;
+            events.push(event);
+        }
+        Ok(SessionLog { header, events })
+    }
+}
+
+/// Apply a logged event to `app`, replaying a session that started at `start`.
+///
+/// Fails when the app diverges from the recording, as happens when the word
+/// lists changed since the session was recorded.
+pub(crate) fn apply_event(app: &mut App, event: &LogEvent, start: Instant) -> Result<()> {
+    let expected = app.session.word_queue.current_word();
+    if expected != event.expected {
+        bail!(
+            "the session expected `{}` at {:.3}s, but the replay expects `{expected}`",
+            event.expected,
+            event.at().as_secs_f64()
+        );
+    }
+    if let Some(policy) = event.mastery {
+        app.session.set_mastery_policy(policy);
+    }
+    app.handle_event(event.event, start + event.at());
+    if app.session.user_input != event.input {
+        bail!(
+            "the session input was `{}` at {:.3}s, but the replay input is `{}`",
+            event.input,
+            event.at().as_secs_f64(),
+            app.session.user_input
+        );
+    }
+    Ok(())
+}
+
+/// The directory session logs are written to, inside the user data directory.
+pub(crate) fn sessions_dir() -> Result<PathBuf> {
+    let data_dir = dirs::data_dir().context("could not determine the user data directory")?;
+    Ok(data_dir.join("dvoratt").join("sessions"))
+}
+
+/// Writes the log of a running session, one line per event.
+pub(crate) struct SessionRecorder {
+    path: PathBuf,
+    writer: BufWriter<File>,
+}
+
+impl SessionRecorder {
+    /// Create a log in `dir`, named after the session start time.
+    pub(crate) fn create_in(dir: &Path, header: &LogHeader) -> Result<Self> {
+        fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+        let name = header.started_at.strftime("%Y%m%dT%H%M%S%.6fZ.jsonl");
+        Self::create(&dir.join(name.to_string()), header)
+    }
+
+    pub(crate) fn create(path: &Path, header: &LogHeader) -> Result<Self> {
+        let file =
+            File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+        let mut recorder = SessionRecorder {
+            path: path.to_path_buf(),
+            writer: BufWriter::new(file),
+        };
+        recorder.write_line(header)?;
+        Ok(recorder)
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append an event, flushing it so that the log survives a crash.
+    pub(crate) fn record(&mut self, event: &LogEvent) -> Result<()> {
+        self.write_line(event)
+    }
+
+    fn write_line(&mut self, value: &impl Serialize) -> Result<()> {
+        serde_json::to_writer(&mut self.writer, value)?;
+        self.writer.write_all(b"\n")?;
+        self.writer
+            .flush()
+            .with_context(|| format!("failed to write {}", self.path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::Command;
+
+    fn header() -> LogHeader {
+        LogHeader {
+            version: LOG_VERSION,
+            started_at: Timestamp::UNIX_EPOCH,
+            options: AppOptions {
+                seed: Some(42),
+                ..AppOptions::default()
+            },
+        }
+    }
+
+    fn event(at_us: u64, event: InputEvent, input: &str) -> LogEvent {
+        LogEvent {
+            at_us,
+            event,
+            expected: "the".to_string(),
+            word: 0,
+            input: input.to_string(),
+            mastery: None,
+        }
+    }
+
+    #[test]
+    fn test_record_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut recorder = SessionRecorder::create_in(dir.path(), &header()).unwrap();
+        let policy = MasteryPolicy {
+            repetitions: 5,
+            ..MasteryPolicy::default()
+        };
+        let events = vec![
+            event(0, InputEvent::Type('t'), "t"),
+            event(150_000, InputEvent::Type('x'), "tx"),
+            event(320_000, InputEvent::DeleteChar, "t"),
+            LogEvent {
+                mastery: Some(policy),
+                ..event(500_000, InputEvent::Command(Command::ToggleHeatmap), "t")
+            },
+        ];
+        for event in &events {
+            recorder.record(event).unwrap();
+        }
+        assert_eq!(
+            recorder.path(),
+            dir.path().join("19700101T000000.000000Z.jsonl")
+        );
+
+        let log = SessionLog::load(recorder.path()).unwrap();
+        assert_eq!(log.header, header());
+        assert_eq!(log.events, events);
+        assert_eq!(log.events[1].at(), Duration::from_millis(150));
+    }
+
+    /// Type `word` and a space, one key every 180 ms from `at`, logging the keys.
+    fn type_word(app: &mut App, start: Instant, at: &mut Duration, word: &str) -> Vec<LogEvent> {
+        let mut events = Vec::new();
+        for c in word.chars().chain([' ']) {
+            *at += Duration::from_millis(180);
+            let expected = app.session.word_queue.current_word().to_string();
+            let index = app.session.word_queue.word_index();
+            app.handle_event(InputEvent::typed(c), start + *at);
+            events.push(LogEvent {
+                at_us: at.as_micros() as u64,
+                event: InputEvent::typed(c),
+                expected,
+                word: index,
+                input: app.session.user_input.clone(),
+                mastery: None,
+            });
+        }
+        events
+    }
+
+    #[test]
+    fn test_replay_reproduces_the_session() {
+        let options = header().options;
+        let mut recorded = App::new(options.clone()).unwrap();
+        let start = Instant::now();
+        let mut at = Duration::ZERO;
+        let mut events = Vec::new();
+        for _ in 0..5 {
+            let word = recorded.session.word_queue.current_word().to_string();
+            events.extend(type_word(&mut recorded, start, &mut at, &word));
+        }
+        events.extend(type_word(&mut recorded, start, &mut at, "xq"));
+
+        let mut replayed = App::new(options).unwrap();
+        let replay_start = Instant::now() + Duration::from_secs(60);
+        for event in &events {
+            apply_event(&mut replayed, event, replay_start).unwrap();
+        }
+        assert_eq!(
+            replayed.final_report(Timestamp::UNIX_EPOCH),
+            recorded.final_report(Timestamp::UNIX_EPOCH)
+        );
+        assert_eq!(
+            replayed.session.word_queue.current_word(),
+            recorded.session.word_queue.current_word()
+        );
+
+        let mut diverged = App::new(AppOptions {
+            seed: Some(7),
+            ..header().options
+        })
+        .unwrap();
+        let mismatch = LogEvent {
+            expected: "not-a-word".to_string(),
+            ..events[0].clone()
+        };
+        assert!(apply_event(&mut diverged, &mismatch, replay_start).is_err());
+    }
+
+    #[test]
+    fn test_replay_applies_reloaded_policy() {
+        let options = header().options;
+        let mut recorded = App::new(options.clone()).unwrap();
+        let start = Instant::now();
+        let mut at = Duration::ZERO;
+        let problem_word = recorded.session.word_queue.current_word().to_string();
+        let mut events = type_word(&mut recorded, start, &mut at, "xq");
+
+        // The config is reloaded: problem words are typed once instead of three times.
+        let policy = MasteryPolicy {
+            repetitions: 1,
+            ..MasteryPolicy::default()
+        };
+        recorded.session.set_mastery_policy(policy);
+        let mut retyped = type_word(&mut recorded, start, &mut at, &problem_word);
+        retyped[0].mastery = Some(policy);
+        events.extend(retyped);
+        assert_ne!(recorded.session.word_queue.current_word(), problem_word);
+
+        let mut replayed = App::new(options).unwrap();
+        for event in &events {
+            apply_event(&mut replayed, event, start).unwrap();
+        }
+        assert_eq!(replayed.session.mastery_policy(), policy);
+        assert_eq!(
+            replayed.session.word_queue.current_word(),
+            recorded.session.word_queue.current_word()
+        );
+    }
+
+    #[test]
+    fn test_unsupported_version_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("log.jsonl");
+        let header = LogHeader {
+            version: LOG_VERSION + 1,
+            ..header()
+        };
+        SessionRecorder::create(&path, &header).unwrap();
+        assert!(SessionLog::load(&path).is_err());
+    }
+
+    #[test]
+    fn test_invalid_event_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("log.jsonl");
+        SessionRecorder::create(&path, &header()).unwrap();
+        let mut contents = fs::read_to_string(&path).unwrap();
+        contents.push_str("{\"at_us\": 1}\n");
+        fs::write(&path, contents).unwrap();
+
+        let err = SessionLog::load(&path).unwrap_err();
+        assert!(format!("{err:#}").contains("line 2"));
+        assert!(SessionLog::load(&dir.path().join("missing.jsonl")).is_err());
+    }
+}
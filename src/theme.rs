@@ -0,0 +1,389 @@
+//! Colour themes of the user interface.
+//!
+//! A [`Palette`] holds the colours of every part of the interface. Dark, light,
+//! high-contrast and colour-blind-safe palettes are built in, and the
+//! configuration file can define its own themes on top of them. A [`Theme`]
+//! turns the palette into the styles the interface draws with. When the
+//! `NO_COLOR` environment variable is set, it draws without colours and marks
+//! mistakes, problem words and the next key with bold, underlined or reversed
+//! text instead.
+
+use std::env;
+
+use ratatui::style::{Color, Modifier, Style};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer};
+
+/// The colours of the user interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Palette {
+    /// The word to type and the selected word list
+    pub(crate) current_word: Color,
+    /// Correctly typed input
+    pub(crate) input: Color,
+    /// Mistyped characters of the input
+    pub(crate) mistyped: Color,
+    /// Speed and session statistics
+    pub(crate) stats: Color,
+    /// Lessons completed and races won
+    pub(crate) success: Color,
+    /// Races lost
+    pub(crate) failure: Color,
+    /// The ghost of a race
+    pub(crate) ghost: Color,
+    /// Background of the next key on the keyboard
+    pub(crate) next_key: Color,
+    /// Backgrounds of the heatmap, from well known keys to the worst ones
+    pub(crate) heat: [Color; 4],
+    /// Text of the heatmap and of the next key
+    pub(crate) heat_text: Color,
+}
+
+impl Palette {
+    /// Colours for dark terminal backgrounds.
+    pub(crate) const DARK: Palette = Palette {
+        current_word: Color::Yellow,
+        input: Color::Green,
+        mistyped: Color::Red,
+        stats: Color::Cyan,
+        success: Color::Green,
+        failure: Color::Red,
+        ghost: Color::Magenta,
+        next_key: Color::Cyan,
+        heat: [Color::Green, Color::Yellow, Color::LightRed, Color::Red],
+        heat_text: Color::Black,
+    };
+
+    /// Colours for light terminal backgrounds.
+    pub(crate) const LIGHT: Palette = Palette {
+        current_word: Color::Blue,
+        input: Color::Rgb(0x1a, 0x7f, 0x37),
+        mistyped: Color::Rgb(0xcf, 0x22, 0x2e),
+        stats: Color::Magenta,
+        success: Color::Rgb(0x1a, 0x7f, 0x37),
+        failure: Color::Rgb(0xcf, 0x22, 0x2e),
+        ghost: Color::Magenta,
+        next_key: Color::LightBlue,
+        heat: [
+            Color::LightGreen,
+            Color::LightYellow,
+            Color::Rgb(0xff, 0xa1, 0x98),
+            Color::Rgb(0xff, 0x5f, 0x57),
+        ],
+        heat_text: Color::Black,
+    };
+
+    /// Bright colours on the terminal background, for low vision.
+    pub(crate) const HIGH_CONTRAST: Palette = Palette {
+        current_word: Color::LightYellow,
+        input: Color::White,
+        mistyped: Color::LightRed,
+        stats: Color::LightCyan,
+        success: Color::LightGreen,
+        failure: Color::LightRed,
+        ghost: Color::LightMagenta,
+        next_key: Color::White,
+        heat: [
+            Color::LightGreen,
+            Color::LightYellow,
+            Color::LightMagenta,
+            Color::LightRed,
+        ],
+        heat_text: Color::Black,
+    };
+
+    /// The Okabe-Ito colours, told apart with any colour vision deficiency.
+    pub(crate) const COLORBLIND: Palette = Palette {
+        current_word: Color::Rgb(0xf0, 0xe4, 0x42),
+        input: Color::Rgb(0x56, 0xb4, 0xe9),
+        mistyped: Color::Rgb(0xd5, 0x5e, 0x00),
+        stats: Color::Rgb(0xcc, 0x79, 0xa7),
+        success: Color::Rgb(0x56, 0xb4, 0xe9),
+        failure: Color::Rgb(0xd5, 0x5e, 0x00),
+        ghost: Color::Rgb(0xcc, 0x79, 0xa7),
+        next_key: Color::White,
+        heat: [
+            Color::Rgb(0x56, 0xb4, 0xe9),
+            Color::Rgb(0xf0, 0xe4, 0x42),
+            Color::Rgb(0xe6, 0x9f, 0x00),
+            Color::Rgb(0xd5, 0x5e, 0x00),
+        ],
+        heat_text: Color::Black,
+    };
+
+    /// Names of the built-in palettes.
+    pub(crate) const NAMES: [&str; 4] = ["dark", "light", "high-contrast", "colorblind"];
+
+    /// The built-in palette called `name`.
+    pub(crate) fn builtin(name: &str) -> Option<Palette> {
+        match name {
+            "dark" => Some(Palette::DARK),
+            "light" => Some(Palette::LIGHT),
+            "high-contrast" => Some(Palette::HIGH_CONTRAST),
+            "colorblind" => Some(Palette::COLORBLIND),
+            _ => None,
+        }
+    }
+
+    /// This palette with the colours `colors` sets.
+    pub(crate) fn with(mut self, colors: &ThemeColors) -> Palette {
+        let overrides = [
+            (&mut self.current_word, colors.current_word),
+            (&mut self.input, colors.input),
+            (&mut self.mistyped, colors.mistyped),
+            (&mut self.stats, colors.stats),
+            (&mut self.success, colors.success),
+            (&mut self.failure, colors.failure),
+            (&mut self.ghost, colors.ghost),
+            (&mut self.next_key, colors.next_key),
+            (&mut self.heat_text, colors.heat_text),
+        ];
+        for (color, color_override) in overrides {
+            if let Some(value) = color_override {
+                *color = value;
+            }
+        }
+        for (color, color_override) in self.heat.iter_mut().zip(colors.heat) {
+            if let Some(value) = color_override {
+                *color = value;
+            }
+        }
+        self
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette::DARK
+    }
+}
+
+/// Colours of a theme of the configuration file, each replacing the colour of
+/// the theme it is based on.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct ThemeColors {
+    /// The theme this one starts from, `dark` by default
+    pub(crate) base: Option<String>,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub(crate) current_word: Option<Color>,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub(crate) input: Option<Color>,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub(crate) mistyped: Option<Color>,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub(crate) stats: Option<Color>,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub(crate) success: Option<Color>,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub(crate) failure: Option<Color>,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub(crate) ghost: Option<Color>,
+    #[serde(deserialize_with = "deserialize_color")]
+    pub(crate) next_key: Option<Color>,
+    /// Heatmap backgrounds, from well known keys to the worst ones
+    #[serde(deserialize_with = "deserialize_heat")]
+    pub(crate) heat: [Option<Color>; 4],
+    #[serde(deserialize_with = "deserialize_color")]
+    pub(crate) heat_text: Option<Color>,
+}
+
+fn parse_color<E: serde::de::Error>(name: &str) -> Result<Color, E> {
+    name.parse()
+        .map_err(|_| E::custom(format!("`{name}` is not a colour")))
+}
+
+/// A colour name such as `light-blue`, an ANSI index or a `#rrggbb` value.
+fn deserialize_color<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Color>, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    parse_color(&name).map(Some)
+}
+
+/// Four colours, from well known keys to the worst ones.
+fn deserialize_heat<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<[Option<Color>; 4], D::Error> {
+    let names = Vec::<String>::deserialize(deserializer)?;
+    let names: [String; 4] = names.try_into().map_err(|names: Vec<String>| {
+        D::Error::custom(format!("`heat` needs 4 colours, not {}", names.len()))
+    })?;
+    let mut heat = [None; 4];
+    for (color, name) in heat.iter_mut().zip(&names) {
+        *color = Some(parse_color(name)?);
+    }
+    Ok(heat)
+}
+
+/// Whether the `NO_COLOR` environment variable asks for output without
+/// colours.
+pub(crate) fn no_color_requested() -> bool {
+    env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+}
+
+/// The styles the interface is drawn with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Theme {
+    /// The colours, or `None` to draw with text modifiers only
+    palette: Option<Palette>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::colored(Palette::DARK)
+    }
+}
+
+impl Theme {
+    pub(crate) fn colored(palette: Palette) -> Self {
+        Theme {
+            palette: Some(palette),
+        }
+    }
+
+    /// A theme without colours.
+    pub(crate) fn monochrome() -> Self {
+        Theme { palette: None }
+    }
+
+    /// A theme with `palette`, unless `NO_COLOR` is set.
+    pub(crate) fn from_env(palette: Palette) -> Self {
+        if no_color_requested() {
+            Theme::monochrome()
+        } else {
+            Theme::colored(palette)
+        }
+    }
+
+    /// The colour `color` picks from the palette, or `plain` without colours.
+    fn style(&self, color: impl Fn(&Palette) -> Color, plain: Modifier) -> Style {
+        match &self.palette {
+            Some(palette) => Style::default().fg(color(palette)),
+            None => Style::default().add_modifier(plain),
+        }
+    }
+
+    pub(crate) fn current_word(&self) -> Style {
+        self.style(|p| p.current_word, Modifier::BOLD)
+    }
+
+    /// The selected word list.
+    pub(crate) fn selected(&self) -> Style {
+        self.style(|p| p.current_word, Modifier::REVERSED)
+    }
+
+    /// A problem word being repeated, or the weakest key of an adaptive lesson.
+    pub(crate) fn problem_word(&self) -> Style {
+        self.style(|p| p.current_word, Modifier::BOLD)
+            .add_modifier(Modifier::UNDERLINED)
+    }
+
+    pub(crate) fn input(&self) -> Style {
+        self.style(|p| p.input, Modifier::empty())
+    }
+
+    /// Mistyped characters and errors.
+    pub(crate) fn mistyped(&self) -> Style {
+        self.style(|p| p.mistyped, Modifier::REVERSED)
+            .add_modifier(Modifier::BOLD)
+    }
+
+    pub(crate) fn stats(&self) -> Style {
+        self.style(|p| p.stats, Modifier::empty())
+    }
+
+    pub(crate) fn success(&self) -> Style {
+        self.style(|p| p.success, Modifier::BOLD)
+    }
+
+    pub(crate) fn failure(&self) -> Style {
+        self.style(|p| p.failure, Modifier::empty())
+    }
+
+    pub(crate) fn ghost(&self) -> Style {
+        self.style(|p| p.ghost, Modifier::empty())
+    }
+
+    /// The next key to type on the keyboard.
+    pub(crate) fn next_key(&self) -> Style {
+        match &self.palette {
+            Some(palette) => Style::default().fg(palette.heat_text).bg(palette.next_key),
+            None => Style::default().add_modifier(Modifier::REVERSED),
+        }
+        .add_modifier(Modifier::BOLD)
+    }
+
+    /// A heatmap cell, from 0 for well known keys to 3 for the worst ones.
+    pub(crate) fn heat(&self, level: usize) -> Style {
+        let level = level.min(3);
+        match &self.palette {
+            Some(palette) => Style::default()
+                .fg(palette.heat_text)
+                .bg(palette.heat[level]),
+            None => Style::default().add_modifier(
+                [
+                    Modifier::empty(),
+                    Modifier::UNDERLINED,
+                    Modifier::BOLD,
+                    Modifier::BOLD | Modifier::UNDERLINED,
+                ][level],
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_palettes() {
+        for name in Palette::NAMES {
+            assert!(Palette::builtin(name).is_some(), "{name}");
+        }
+        assert_eq!(Palette::builtin("dark"), Some(Palette::default()));
+        assert_eq!(Palette::builtin("solarized"), None);
+    }
+
+    #[test]
+    fn test_palette_with_overrides() {
+        let colors: ThemeColors = toml::from_str(
+            "mistyped = \"#ff8800\"\nheat = [\"blue\", \"cyan\", \"magenta\", \"red\"]",
+        )
+        .unwrap();
+        let palette = Palette::LIGHT.with(&colors);
+        assert_eq!(palette.mistyped, Color::Rgb(0xff, 0x88, 0x00));
+        assert_eq!(palette.heat[1], Color::Cyan);
+        assert_eq!(palette.current_word, Palette::LIGHT.current_word);
+
+        assert!(toml::from_str::<ThemeColors>("input = \"not a colour\"").is_err());
+        assert!(toml::from_str::<ThemeColors>("heat = [\"red\"]").is_err());
+    }
+
+    #[test]
+    fn test_monochrome_uses_modifiers() {
+        let theme = Theme::monochrome();
+        for style in [
+            theme.mistyped(),
+            theme.problem_word(),
+            theme.next_key(),
+            theme.heat(3),
+        ] {
+            assert_eq!(style.fg, None);
+            assert_eq!(style.bg, None);
+            assert!(!style.add_modifier.is_empty());
+        }
+        assert!(theme.mistyped().add_modifier.contains(Modifier::REVERSED));
+        assert!(
+            theme
+                .problem_word()
+                .add_modifier
+                .contains(Modifier::UNDERLINED)
+        );
+
+        let colored = Theme::colored(Palette::DARK);
+        assert_eq!(colored.mistyped().fg, Some(Color::Red));
+        assert_eq!(colored.heat(0).bg, Some(Color::Green));
+    }
+}
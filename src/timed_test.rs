@@ -0,0 +1,137 @@
+//! Fixed-length typing tests.
+//!
+//! A timed test starts its clock on the first keystroke and finishes once its
+//! duration has elapsed. The results only cover the keystrokes typed during
+//! that window.
+
+use std::time::{Duration, Instant};
+
+use crate::performance::KeystrokeStats;
+
+/// The test lengths offered on the command line, in seconds.
+pub(crate) const TEST_DURATIONS: [u64; 4] = [15, 30, 60, 120];
+
+/// State of a timed test.
+pub(crate) struct TimedTest {
+    duration: Duration,
+    started_at: Option<Instant>,
+    baseline: KeystrokeStats,
+    results: Option<KeystrokeStats>,
+}
+
+impl TimedTest {
+    pub(crate) fn new(duration: Duration) -> Self {
+        TimedTest {
+            duration,
+            started_at: None,
+            baseline: KeystrokeStats::default(),
+            results: None,
+        }
+    }
+
+    /// Start the clock unless it is already running.
+    ///
+    /// `stats` are the session counters at that moment, so that the results
+    /// only include what is typed from now on.
+    pub(crate) fn start_if_needed(&mut self, now: Instant, stats: KeystrokeStats) {
+        if self.started_at.is_none() {
+            self.started_at = Some(now);
+            self.baseline = stats;
+        }
+    }
+
+    /// Finish the test if its time is up, freezing the results.
+    ///
+    /// Returns `true` once the test is finished.
+    pub(crate) fn update(&mut self, now: Instant, stats: KeystrokeStats) -> bool {
+        if self.results.is_none() && self.remaining(now) == Some(Duration::ZERO) {
+            self.results = Some(stats.since(&self.baseline));
+        }
+        self.is_finished()
+    }
+
+    pub(crate) fn is_finished(&self) -> bool {
+        self.results.is_some()
+    }
+
+    pub(crate) fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Time left on the clock, or `None` if the test has not started.
+    pub(crate) fn remaining(&self, now: Instant) -> Option<Duration> {
+        self.started_at.map(|started_at| {
+            self.duration
+                .saturating_sub(now.saturating_duration_since(started_at))
+        })
+    }
+
+    /// The keystrokes typed during the test, once it is finished.
+    pub(crate) fn results(&self) -> Option<&KeystrokeStats> {
+        self.results.as_ref()
+    }
+
+    /// Prepare a new test of the same duration.
+    pub(crate) fn reset(&mut self) {
+        *self = Self::new(self.duration);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(total_keystrokes: u32) -> KeystrokeStats {
+        KeystrokeStats {
+            total_keystrokes,
+            correct_keystrokes: total_keystrokes,
+            ..KeystrokeStats::default()
+        }
+    }
+
+    #[test]
+    fn test_clock_starts_on_first_keystroke() {
+        let start = Instant::now();
+        let mut test = TimedTest::new(Duration::from_secs(15));
+        assert_eq!(test.remaining(start), None);
+
+        test.start_if_needed(start, stats(0));
+        test.start_if_needed(start + Duration::from_secs(5), stats(3));
+        assert_eq!(
+            test.remaining(start + Duration::from_secs(5)),
+            Some(Duration::from_secs(10))
+        );
+    }
+
+    #[test]
+    fn test_finishes_after_duration() {
+        let start = Instant::now();
+        let mut test = TimedTest::new(Duration::from_secs(15));
+        test.start_if_needed(start, stats(10));
+
+        assert!(!test.update(start + Duration::from_secs(14), stats(40)));
+        assert!(test.update(start + Duration::from_secs(15), stats(50)));
+        // Keystrokes after the end are not counted.
+        assert!(test.update(start + Duration::from_secs(16), stats(60)));
+        assert_eq!(test.results().unwrap().total_keystrokes, 40);
+    }
+
+    #[test]
+    fn test_not_finished_before_start() {
+        let mut test = TimedTest::new(Duration::from_secs(15));
+        assert!(!test.update(Instant::now() + Duration::from_secs(60), stats(0)));
+    }
+
+    #[test]
+    fn test_reset() {
+        let start = Instant::now();
+        let mut test = TimedTest::new(Duration::from_secs(30));
+        test.start_if_needed(start, stats(0));
+        test.update(start + Duration::from_secs(30), stats(5));
+
+        test.reset();
+        assert!(!test.is_finished());
+        assert_eq!(test.remaining(start), None);
+        assert_eq!(test.duration(), Duration::from_secs(30));
+    }
+}
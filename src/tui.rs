@@ -0,0 +1,586 @@
+//! The terminal application: command dispatch, practice sessions in the
+//! terminal interface, replays and the commands printing to the console.
+
+use anyhow::{Context, Result, bail};
+use clap::Parser;
+use crossterm::{
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyModifiers,
+    },
+    execute,
+    terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
+};
+use jiff::{Timestamp, tz::TimeZone};
+use ratatui::{Terminal, backend::CrosstermBackend};
+use std::fs;
+use std::io::Stdout;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::app::{App, AppOptions};
+use crate::cli::{
+    Cli, Command, ExportArgs, GenerateArgs, GhostSource, LessonsCommand, PracticeArgs, ReplayArgs,
+};
+use crate::clock::{Clock, ManualClock};
+use crate::config::{Config, ConfigWatcher, config_path};
+use crate::ghost::GhostRace;
+use crate::history::HistoryStore;
+use crate::input::{self, InputEvent, KeyChord, KeyName};
+use crate::lesson_generator::{UnlockSchedule, generate_lessons, write_lessons};
+use crate::report::{ReportDocument, SessionReport, export_sessions};
+use crate::review::{ReviewDeck, ReviewStore};
+use crate::session_log::{
+    LOG_VERSION, LogEvent, LogHeader, SessionLog, SessionRecorder, apply_event, sessions_dir,
+};
+use crate::ui;
+use crate::word_lists::{load_word_list_file, load_word_lists, user_lessons_dir};
+
+/// Run the Dvorak typing practice application.
+///
+/// Command-line arguments are read from the process environment.
+pub fn run() -> Result<()> {
+    let cli = Cli::parse();
+    let word_paths = word_paths(cli.word_paths);
+    match cli.command {
+        None => practice(&PracticeArgs::default(), word_paths),
+        Some(Command::Practice(args)) => practice(&args, word_paths),
+        Some(Command::Lessons(LessonsCommand::List)) => list_lessons(&word_paths),
+        Some(Command::Lessons(LessonsCommand::Generate(args))) => generate(&args),
+        Some(Command::Replay(args)) => replay(&args),
+        Some(Command::Stats) => show_stats(),
+        Some(Command::Export(args)) => export(&args),
+    }
+}
+
+/// The user lessons directory, if it exists, followed by the paths given on
+/// the command line.
+fn word_paths(cli_paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    user_lessons_dir()
+        .filter(|dir| dir.is_dir())
+        .into_iter()
+        .chain(cli_paths)
+        .collect()
+}
+
+fn list_lessons(word_paths: &[PathBuf]) -> Result<()> {
+    for (index, list) in load_word_lists(word_paths)?.iter().enumerate() {
+        println!("{}\t{}\t{} words", index + 1, list.name, list.words.len());
+    }
+    Ok(())
+}
+
+fn generate(args: &GenerateArgs) -> Result<()> {
+    let layout = args.layout.clone().unwrap_or_default();
+    let schedule = match &args.schedule {
+        Some(spec) => UnlockSchedule::parse(spec, &layout).context("invalid unlock schedule")?,
+        None => UnlockSchedule::for_layout(&layout),
+    };
+    let dictionary = load_word_list_file(&args.dictionary)?;
+    let lessons = generate_lessons(&dictionary.words, &schedule)?;
+    let paths = write_lessons(&lessons, &args.output)?;
+    for (lesson, path) in lessons.iter().zip(&paths) {
+        println!(
+            "{}\t+{}\t{} words",
+            path.display(),
+            lesson.new_keys.iter().collect::<String>(),
+            lesson.words.len()
+        );
+    }
+    Ok(())
+}
+
+fn show_stats() -> Result<()> {
+    let store = HistoryStore::open_default()?;
+    let history = store.load()?;
+    if history.sessions.is_empty() {
+        println!("No sessions recorded yet in {}.", store.path().display());
+        return Ok(());
+    }
+
+    let tz = TimeZone::system();
+    for session in &history.sessions {
+        let seed = session
+            .seed
+            .map_or_else(|| "-".to_string(), |seed| seed.to_string());
+        println!(
+            "{}\tlevel {}\t{:.2} WPM\t{:.1}% accuracy\t{} problem words\tseed {seed}",
+            session
+                .timestamp
+                .to_zoned(tz.clone())
+                .strftime("%Y-%m-%d %H:%M"),
+            session.level,
+            session.average_wpm,
+            session.keystrokes.accuracy,
+            session.problem_words.len()
+        );
+    }
+
+    let count = history.sessions.len();
+    let mean = history.sessions.iter().map(|s| s.average_wpm).sum::<f32>() / count as f32;
+    let best = history
+        .sessions
+        .iter()
+        .map(|s| s.average_wpm)
+        .fold(0.0, f32::max);
+    println!("{count} sessions, {mean:.2} WPM on average, best {best:.2} WPM");
+    Ok(())
+}
+
+fn export(args: &ExportArgs) -> Result<()> {
+    let history = HistoryStore::open_default()?.load()?;
+    let tz = TimeZone::system();
+    let since = match args.since {
+        Some(date) => Some(
+            date.to_zoned(tz.clone())
+                .with_context(|| format!("{date} does not exist in the local time zone"))?
+                .timestamp(),
+        ),
+        None => None,
+    };
+    let sessions: Vec<_> = history
+        .sessions
+        .into_iter()
+        .filter(|session| since.is_none_or(|since| session.timestamp >= since))
+        .collect();
+    let export = export_sessions(&sessions, args.format, &tz)?;
+    match &args.output {
+        Some(path) => {
+            fs::write(path, export).with_context(|| format!("failed to write {}", path.display()))
+        }
+        None => {
+            print!("{export}");
+            Ok(())
+        }
+    }
+}
+
+fn practice(args: &PracticeArgs, word_paths: Vec<PathBuf>) -> Result<()> {
+    let config = ConfigWatcher::open_default()?;
+    let review_store = ReviewStore::open_default()?;
+    let review_deck = review_store.load()?;
+    let list_index = args
+        .level
+        .or(config.config().level)
+        .map_or(AppOptions::default().list_index, |level| {
+            usize::from(level) - 1
+        });
+    let mut practice = Practice {
+        args,
+        word_paths,
+        ghost: args.ghost.as_ref().map(load_ghost).transpose()?,
+        sessions_dir: sessions_dir()?,
+        review_store,
+        review_deck,
+        config,
+        reports: Vec::new(),
+    };
+    // Build the app before touching the terminal so that argument errors are
+    // reported on a regular screen.
+    let (app, recorder) = practice.start_session(list_index)?;
+
+    let mut terminal = setup_terminal()?;
+    let res = practice.run(&mut terminal, app, recorder);
+    restore_terminal(&mut terminal)?;
+
+    if let Err(err) = res {
+        println!("{err:?}");
+    }
+    if args.json {
+        print_report(&ReportDocument::new(practice.reports))?;
+    }
+    Ok(())
+}
+
+/// What the user picked on the results screen.
+enum ResultsAction {
+    Restart,
+    ChangeLevel(usize),
+    Quit,
+}
+
+/// The successive sessions of `dvoratt practice`, restarted from the results
+/// screen.
+struct Practice<'a> {
+    args: &'a PracticeArgs,
+    word_paths: Vec<PathBuf>,
+    /// The recorded session raced against, until the level changes
+    ghost: Option<SessionLog>,
+    sessions_dir: PathBuf,
+    review_store: ReviewStore,
+    review_deck: ReviewDeck,
+    /// The configuration file, reloaded while sessions run
+    config: ConfigWatcher,
+    /// Statistics of the finished sessions, printed with `--json` and written
+    /// to `--output`
+    reports: Vec<SessionReport>,
+}
+
+impl Practice<'_> {
+    /// Create the app of a new session on the word list at `list_index`, and
+    /// its keystroke log.
+    fn start_session(&self, list_index: usize) -> Result<(App, SessionRecorder)> {
+        let options = match &self.ghost {
+            // Race on the ghost's words, from the options it was recorded with.
+            Some(log) => log.header.options.clone(),
+            None => {
+                let config = self.config.config();
+                let seed = self.args.seed.or(config.seed);
+                AppOptions {
+                    list_index,
+                    // Always seed the word order, so that the session log can replay it.
+                    seed: Some(seed.unwrap_or_else(rand::random)),
+                    word_paths: self.word_paths.clone(),
+                    time_limit: self.args.time.or(config.time).map(Duration::from_secs),
+                    layout: self
+                        .args
+                        .layout
+                        .as_ref()
+                        .or(config.layout())
+                        .cloned()
+                        .unwrap_or_default(),
+                    // A level on the command line overrides an adaptive config.
+                    adaptive: self.args.adaptive || (config.adaptive && self.args.level.is_none()),
+                    // A seed given by the user leaves reviews out, so that everyone
+                    // taking the test with it types the same words.
+                    review_words: match seed {
+                        Some(_) => Vec::new(),
+                        None => self.review_deck.due_words(Timestamp::now()),
+                    },
+                    mastery: config.mastery,
+                    input_mode: self
+                        .args
+                        .input_mode
+                        .or(config.input_mode)
+                        .unwrap_or_default(),
+                }
+            }
+        };
+        let header = LogHeader {
+            version: LOG_VERSION,
+            started_at: Timestamp::now(),
+            options: options.clone(),
+        };
+        let mut app = App::new(options)?;
+        app.apply_display_config(self.config.config());
+        if let Some(log) = &self.ghost {
+            app.ghost_race = Some(GhostRace::from_events(&log.events)?);
+        }
+        let recorder = SessionRecorder::create_in(&self.sessions_dir, &header)
+            .context("failed to start the session log")?;
+        Ok((app, recorder))
+    }
+
+    /// Run sessions until the user quits from the results screen.
+    fn run<B: ratatui::backend::Backend>(
+        &mut self,
+        terminal: &mut Terminal<B>,
+        mut app: App,
+        mut recorder: SessionRecorder,
+    ) -> Result<()>
+    where
+        B::Error: 'static + Send + Sync,
+    {
+        loop {
+            run_app(terminal, &mut app, &mut recorder, &mut self.config)?;
+            self.finish_session(&app, &recorder)?;
+            let list_index = match run_results(terminal, &app, Some(recorder.path()), true)? {
+                ResultsAction::Quit => return Ok(()),
+                ResultsAction::Restart => app.current_list_index,
+                ResultsAction::ChangeLevel(index) => {
+                    self.ghost = None;
+                    index
+                }
+            };
+            // Start from the latest config, and show why it was rejected if it was.
+            let config_error = self.config.poll().err();
+            (app, recorder) = self.start_session(list_index)?;
+            app.config_error = config_error.map(|err| format!("{err:#}"));
+        }
+    }
+
+    /// Save a finished session to the history and its reviews to the deck.
+    fn finish_session(&mut self, app: &App, recorder: &SessionRecorder) -> Result<()> {
+        self.reports.push(app.final_report(Timestamp::now()));
+        if let Some(path) = &self.args.output {
+            write_report(path, &ReportDocument::new(self.reports.clone()))?;
+        }
+        if app.session.performance.has_completed_words() {
+            HistoryStore::open_default()?
+                .append(app.session_record(Timestamp::now(), Some(recorder.path().to_path_buf())))
+                .context("failed to save the session history")?;
+        }
+        if !app.session.review_grades().is_empty() {
+            let now = Timestamp::now();
+            for (word, grade) in app.session.review_grades() {
+                self.review_deck.review(word, *grade, now);
+            }
+            self.review_store.save(&self.review_deck).with_context(|| {
+                format!(
+                    "failed to save the review schedule to {}",
+                    self.review_store.path().display()
+                )
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// The session log a ghost race replays.
+///
+/// Adaptive sessions are refused, as their words depend on the typing.
+fn load_ghost(source: &GhostSource) -> Result<SessionLog> {
+    let path = match source {
+        GhostSource::Log(path) => path.clone(),
+        GhostSource::Best => {
+            let store = HistoryStore::open_default()?;
+            let history = store.load()?;
+            let path = history.best_log_file().with_context(|| {
+                format!(
+                    "no non-adaptive session of {} has a keystroke log to race against",
+                    store.path().display()
+                )
+            })?;
+            path.to_path_buf()
+        }
+    };
+    let log = SessionLog::load(&path)?;
+    if log.header.options.adaptive {
+        bail!(
+            "{} is an adaptive session, whose words follow the typing and cannot be raced",
+            path.display()
+        );
+    }
+    Ok(log)
+}
+
+fn replay(args: &ReplayArgs) -> Result<()> {
+    let log = SessionLog::load(&args.file)?;
+    // The replay reads the time of the recording, whatever its speed.
+    let clock = ManualClock::new();
+    let mut app = App::new(log.header.options.clone())
+        .context("failed to set up the recorded session")?
+        .with_clock(clock.clone());
+    app.apply_display_config(&Config::load(&config_path()?)?);
+
+    let mut terminal = setup_terminal()?;
+    let res = run_replay(&mut terminal, &mut app, &clock, &log.events, args.speed)
+        .and_then(|()| run_results(&mut terminal, &app, Some(&args.file), false));
+    restore_terminal(&mut terminal)?;
+
+    res?;
+    if args.json {
+        // The replayed session ended with its last recorded key.
+        let ended_at = match log.events.last() {
+            Some(event) => log.header.started_at.checked_add(event.at())?,
+            None => log.header.started_at,
+        };
+        print_report(&ReportDocument::new(vec![app.final_report(ended_at)]))?;
+    }
+    Ok(())
+}
+
+fn print_report(document: &ReportDocument) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(document)?);
+    Ok(())
+}
+
+fn write_report(path: &Path, document: &ReportDocument) -> Result<()> {
+    let contents = serde_json::to_string_pretty(document)?;
+    fs::write(path, contents + "\n")
+        .with_context(|| format!("failed to write the session report to {}", path.display()))
+}
+
+fn setup_terminal() -> Result<Terminal<CrosstermBackend<Stdout>>> {
+    enable_raw_mode()?;
+    let mut stdout = std::io::stdout();
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    Ok(Terminal::new(CrosstermBackend::new(stdout))?)
+}
+
+fn restore_terminal(terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<()> {
+    disable_raw_mode()?;
+    execute!(
+        terminal.backend_mut(),
+        LeaveAlternateScreen,
+        DisableMouseCapture
+    )?;
+    terminal.show_cursor()?;
+    Ok(())
+}
+
+const TICK_RATE: Duration = Duration::from_millis(250);
+
+/// The chord of a terminal key, or `None` for keys that cannot be bound.
+fn key_chord(key: &KeyEvent) -> Option<KeyChord> {
+    let name = match key.code {
+        KeyCode::Char(c) => KeyName::Char(c),
+        KeyCode::Backspace => KeyName::Backspace,
+        KeyCode::Delete => KeyName::Delete,
+        KeyCode::Enter => KeyName::Enter,
+        KeyCode::Esc => KeyName::Esc,
+        KeyCode::Tab => KeyName::Tab,
+        KeyCode::BackTab => KeyName::BackTab,
+        KeyCode::F(n) => KeyName::F(n),
+        _ => return None,
+    };
+    Some(KeyChord::new(
+        name,
+        key.modifiers.contains(KeyModifiers::CONTROL),
+        key.modifiers.contains(KeyModifiers::ALT),
+    ))
+}
+
+fn run_app<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    recorder: &mut SessionRecorder,
+    config: &mut ConfigWatcher,
+) -> Result<()>
+where
+    B::Error: 'static + Send + Sync,
+{
+    let session_start = app.now();
+    // The settings a session starts from only apply to the next one.
+    let started_from = config.config().clone();
+    let mut last_tick = Instant::now();
+    // Mastery policy reloaded since the last logged key, logged with
+    // the next one so that replays apply them at the same point.
+    let mut reloaded_policy = None;
+
+    loop {
+        terminal
+            .draw(|f| ui::draw(f, app))
+            .map_err(anyhow::Error::new)?;
+
+        let timeout = TICK_RATE
+            .checked_sub(last_tick.elapsed())
+            .unwrap_or(Duration::ZERO);
+
+        if crossterm::event::poll(timeout)?
+            && let Event::Key(key) = event::read()?
+            && let Some(event) = key_chord(&key).and_then(|chord| app.keymap.event(chord))
+        {
+            if event == InputEvent::Quit {
+                return Ok(());
+            }
+            let now = app.now();
+            let expected = app.session.word_queue.current_word().to_string();
+            let word = app.session.word_queue.word_index();
+            app.handle_event(event, now);
+            recorder.record(&LogEvent {
+                at_us: now.duration_since(session_start).as_micros() as u64,
+                event,
+                expected,
+                word,
+                input: app.session.user_input.clone(),
+                mastery: reloaded_policy.take(),
+            })?;
+        }
+
+        if last_tick.elapsed() >= TICK_RATE {
+            match config.poll() {
+                Ok(true) => {
+                    if app.apply_config(config.config()) {
+                        reloaded_policy = Some(app.session.mastery_policy());
+                    }
+                    app.next_session_changes = config.config().next_session_changes(&started_from);
+                    app.config_error = None;
+                }
+                Ok(false) => {}
+                Err(err) => app.config_error = Some(format!("{err:#}")),
+            }
+            app.on_tick();
+            last_tick = Instant::now();
+        }
+    }
+}
+
+/// Show the results of a finished session until the user picks what to do
+/// next. Without `can_restart`, the only choice is to quit.
+fn run_results<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    app: &App,
+    log_file: Option<&Path>,
+    can_restart: bool,
+) -> Result<ResultsAction>
+where
+    B::Error: 'static + Send + Sync,
+{
+    let list_count = app.word_lists.len();
+    let can_change_level = can_restart && app.session.word_queue.adaptive_lesson().is_none();
+    loop {
+        terminal
+            .draw(|f| ui::draw_results(f, app, log_file, can_restart))
+            .map_err(anyhow::Error::new)?;
+
+        if let Event::Key(key) = event::read()?
+            && let Some(chord) = key_chord(&key)
+        {
+            match app.keymap.event(chord) {
+                Some(InputEvent::Quit) => return Ok(ResultsAction::Quit),
+                Some(InputEvent::Command(input::Command::Restart)) if can_restart => {
+                    return Ok(ResultsAction::Restart);
+                }
+                Some(InputEvent::NextList) if can_change_level => {
+                    return Ok(ResultsAction::ChangeLevel(
+                        (app.current_list_index + 1) % list_count,
+                    ));
+                }
+                Some(InputEvent::PrevList) if can_change_level => {
+                    return Ok(ResultsAction::ChangeLevel(
+                        (app.current_list_index + list_count - 1) % list_count,
+                    ));
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Play `events` back at `speed` times their recorded pace, until the user
+/// quits with the key bound to `quit`. `clock` is the clock of `app`, moved along
+/// the recording.
+fn run_replay<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    clock: &ManualClock,
+    events: &[LogEvent],
+    speed: f64,
+) -> Result<()>
+where
+    B::Error: 'static + Send + Sync,
+{
+    // Events are applied at their recorded offsets from `start`, so that the
+    // statistics match the recording whatever the playback speed.
+    let start = clock.now();
+    let played_at = Instant::now();
+    let mut pending = events.iter().peekable();
+
+    loop {
+        let elapsed = played_at.elapsed().mul_f64(speed);
+        while let Some(event) = pending.next_if(|event| event.at() <= elapsed) {
+            apply_event(app, event, start)?;
+        }
+        clock.set(start + elapsed);
+        app.on_tick();
+
+        terminal
+            .draw(|f| ui::draw(f, app))
+            .map_err(anyhow::Error::new)?;
+
+        let timeout = pending.peek().map_or(TICK_RATE, |event| {
+            event
+                .at()
+                .saturating_sub(elapsed)
+                .div_f64(speed)
+                .min(TICK_RATE)
+        });
+        if crossterm::event::poll(timeout)?
+            && let Event::Key(key) = event::read()?
+            && let Some(chord) = key_chord(&key)
+            && app.keymap.event(chord) == Some(InputEvent::Quit)
+        {
+            return Ok(());
+        }
+    }
+}
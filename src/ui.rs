@@ -1,26 +1,53 @@
 //! User interface rendering for the Dvorak typing practice application.
 //!
 //! This module contains functions for drawing the terminal UI, including:
-//! - Word list tabs for navigation
+//! - Word list tabs for navigation, or the unlocked keys in adaptive mode
 //! - Typing area with current and next words
 //! - Performance statistics display
 //! - Problem word tracking
 //! - Struggle combination visualization
+//! - Countdown and results of timed tests
+//! - Progress of a race against a recorded session
+//! - Results of a finished session, with its speed over time
+//! - Keyboard heatmap of per-key and per-finger error rate or latency
 //!
 //! The UI is built using ratatui (formerly tui-rs) and provides a clean,
 //! organized interface for the typing practice session.
 
 use ratatui::{
     Frame,
+    buffer::Buffer,
     layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
+    style::{Modifier, Style},
+    symbols::Marker,
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, Paragraph, Tabs},
+    widgets::{
+        Axis, Block, Borders, Chart, Dataset, GraphType, List, ListItem, Paragraph, Tabs, Widget,
+    },
 };
 
-use crate::app::App;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::adaptive::AdaptiveLesson;
+use crate::app::{App, HeatmapMode};
+use crate::engine::InputMode;
+use crate::ghost::{GhostRace, RaceResult};
+use crate::input::{Command, InputEvent};
+use crate::keyboard_layout::{Finger, KeyboardLayout};
+use crate::performance::{KeyStats, KeyStatsEntry};
+use crate::theme::Theme;
+use crate::timed_test::TimedTest;
 
 pub(crate) fn draw(f: &mut Frame, app: &App) {
+    let panels = app.panels;
+    let keyboard_height = if panels.keyboard {
+        KeyboardHeatmap::height(&app.session.layout) + 2
+    } else if panels.session {
+        5
+    } else {
+        0
+    };
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .margin(1)
@@ -28,16 +55,73 @@ pub(crate) fn draw(f: &mut Frame, app: &App) {
             Constraint::Length(3),
             Constraint::Length(3),
             Constraint::Length(3),
+            Constraint::Length(keyboard_height),
             Constraint::Min(0),
         ])
         .split(f.area());
 
     draw_word_list_tabs(f, app, chunks[0]);
+    if let Some(test) = app
+        .session
+        .timed_test
+        .as_ref()
+        .filter(|test| test.is_finished())
+    {
+        let race = app.ghost_race.as_ref().and_then(GhostRace::result);
+        draw_timed_test_results(f, app, test, race, chunks[1].union(chunks[4]));
+        return;
+    }
     draw_typing_area(f, app, chunks[1], chunks[2]);
-    draw_stats(f, app, chunks[3]);
+
+    let keyboard_width = if panels.keyboard {
+        KeyboardHeatmap::width(&app.session.layout) + 2
+    } else {
+        0
+    };
+    let keyboard_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Length(keyboard_width), Constraint::Min(0)])
+        .split(chunks[3]);
+    if panels.keyboard {
+        draw_keyboard(f, app, keyboard_chunks[0]);
+    }
+    if panels.session {
+        draw_session_metrics(f, app, keyboard_chunks[1]);
+    }
+
+    if panels.stats {
+        draw_stats(f, app, chunks[4]);
+    }
+}
+
+/// The title of the top panel, followed by the error of the config file if
+/// its last change was rejected, or else by the changed settings left to the
+/// next session.
+fn top_title(app: &App, title: &str) -> Line<'static> {
+    match &app.config_error {
+        Some(err) => Line::from(vec![
+            Span::raw(format!("{title} ")),
+            Span::styled(format!("config not reloaded: {err}"), app.theme.mistyped()),
+        ]),
+        None if !app.next_session_changes.is_empty() => Line::from(vec![
+            Span::raw(format!("{title} ")),
+            Span::styled(
+                format!(
+                    "{} changed: applies from the next session",
+                    app.next_session_changes.join(", ")
+                ),
+                app.theme.stats(),
+            ),
+        ]),
+        None => Line::raw(title.to_string()),
+    }
 }
 
 fn draw_word_list_tabs(f: &mut Frame, app: &App, area: Rect) {
+    if let Some(lesson) = app.session.word_queue.adaptive_lesson() {
+        draw_unlocked_keys(f, app, lesson, area);
+        return;
+    }
     let list_names: Vec<String> = app
         .word_lists
         .iter()
@@ -45,22 +129,58 @@ fn draw_word_list_tabs(f: &mut Frame, app: &App, area: Rect) {
         .collect();
     let tabs = Tabs::new(list_names)
         .select(app.current_list_index)
-        .block(Block::default().borders(Borders::ALL).title("Word Lists"))
-        .highlight_style(Style::default().fg(Color::Yellow))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(top_title(app, "Word Lists")),
+        )
+        .highlight_style(app.theme.selected())
         .divider("|");
 
     f.render_widget(tabs, area);
 }
 
-fn generate_styled_input<'a>(input: &'a str, mistyped_chars: &'a [usize]) -> Vec<Span<'a>> {
+/// The keys of an adaptive lesson, with the weakest key highlighted and the
+/// next key to unlock shown dimmed.
+fn draw_unlocked_keys(f: &mut Frame, app: &App, lesson: &AdaptiveLesson, area: Rect) {
+    let mut spans: Vec<Span> = lesson
+        .unlocked_keys()
+        .iter()
+        .map(|&key| {
+            let style = if lesson.weakest_key() == Some(key) {
+                app.theme.problem_word().add_modifier(Modifier::BOLD)
+            } else {
+                Style::default()
+            };
+            Span::styled(format!("{key} "), style)
+        })
+        .collect();
+    spans.push(match lesson.next_key() {
+        Some(key) => Span::styled(
+            format!("| next: {key}"),
+            Style::default().add_modifier(Modifier::DIM),
+        ),
+        None => Span::styled("| all keys unlocked", app.theme.success()),
+    });
+    let keys = Paragraph::new(Line::from(spans)).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(top_title(app, "Adaptive Keys (weakest underlined)")),
+    );
+    f.render_widget(keys, area);
+}
+
+fn generate_styled_input<'a>(
+    input: &'a str,
+    mistyped_chars: &'a [usize],
+    mistyped_style: Style,
+) -> Vec<Span<'a>> {
     input
-        .char_indices()
+        .chars()
+        .enumerate()
         .map(|(i, c)| {
             if mistyped_chars.contains(&i) {
-                Span::styled(
-                    c.to_string(),
-                    Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
-                )
+                Span::styled(c.to_string(), mistyped_style)
             } else {
                 Span::raw(c.to_string())
             }
@@ -74,62 +194,521 @@ fn draw_typing_area(f: &mut Frame, app: &App, current_chunk: Rect, input_chunk:
         .constraints([Constraint::Percentage(80), Constraint::Percentage(20)])
         .split(current_chunk);
 
-    let current_word = app.word_queue.current_word().to_string();
-    let next_word = if app.word_queue.is_current_word_problem() {
+    let current_word = app.session.word_queue.current_word().to_string();
+    let next_word = if app.session.word_queue.is_current_word_problem() {
         String::new()
     } else {
-        app.word_queue
+        app.session
+            .word_queue
             .next_words()
             .first()
             .cloned()
             .unwrap_or_default()
     };
 
-    let repetition_count = if app.word_queue.is_current_word_problem() {
+    let repetition_count = if app.session.word_queue.is_current_word_problem() {
         format!(
             " ({})",
-            app.word_queue.get_current_problem_word_repetitions() + 1
+            app.session
+                .word_queue
+                .get_current_problem_word_repetitions()
+                + 1
         )
     } else {
         String::new()
     };
 
     let words_to_type = vec![
-        if app.word_queue.is_current_word_problem() {
+        if app.session.word_queue.is_current_word_problem() {
             Span::styled(
                 format!("{current_word}{repetition_count}"),
-                Style::default()
-                    .fg(Color::Yellow)
-                    .add_modifier(Modifier::UNDERLINED),
+                app.theme.problem_word(),
             )
         } else {
-            Span::styled(current_word, Style::default().fg(Color::Yellow))
+            Span::styled(current_word, app.theme.current_word())
         },
         Span::raw(" "),
         Span::styled(next_word, Style::default().add_modifier(Modifier::DIM)),
     ];
 
-    let words_paragraph = Paragraph::new(Line::from(words_to_type)).block(
+    let title = match &app.session.timed_test {
+        Some(test) => match test.remaining(app.session_now()) {
+            Some(remaining) => format!("Words to Type ({}s left)", remaining.as_secs_f32().ceil()),
+            None => format!(
+                "Words to Type ({}s test, starts on first key)",
+                test.duration().as_secs()
+            ),
+        },
+        None => "Words to Type".to_string(),
+    };
+    let words_paragraph = Paragraph::new(Line::from(words_to_type))
+        .block(Block::default().borders(Borders::ALL).title(title));
+    f.render_widget(words_paragraph, typing_area[0]);
+
+    let avg_speed = format!("{:.2} WPM", app.average_speed_last_10_words());
+    let avg_speed_paragraph = Paragraph::new(avg_speed).style(app.theme.stats()).block(
         Block::default()
             .borders(Borders::ALL)
-            .title("Words to Type"),
+            .title("Avg Speed (Last 10 Words)"),
     );
-    f.render_widget(words_paragraph, typing_area[0]);
+    f.render_widget(avg_speed_paragraph, typing_area[1]);
+    let input_area = match &app.ghost_race {
+        Some(race) => {
+            let input_chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(80), Constraint::Percentage(20)])
+                .split(input_chunk);
+            draw_ghost_race(f, &app.theme, race, app.session_now(), input_chunks[1]);
+            input_chunks[0]
+        }
+        None => input_chunk,
+    };
+    let styled_input = generate_styled_input(
+        &app.session.user_input,
+        app.session.performance.mistyped_chars(),
+        app.theme.mistyped(),
+    );
+    let input_title = if app.is_paused() {
+        format!(
+            "Your Input (paused, {} to resume)",
+            app.keymap.hint(InputEvent::Pause)
+        )
+    } else {
+        match app.session.input_mode() {
+            InputMode::StopOnLetter => "Your Input (stop on letter)".to_string(),
+            InputMode::StopOnWord => "Your Input (stop on word)".to_string(),
+            InputMode::Free => "Your Input".to_string(),
+        }
+    };
+    let user_input = Paragraph::new(Line::from(styled_input))
+        .style(app.theme.input())
+        .block(Block::default().borders(Borders::ALL).title(input_title));
+    f.render_widget(user_input, input_area);
+}
 
-    let avg_speed = format!("{:.2} WPM", app.average_speed_last_10_words());
-    let avg_speed_paragraph = Paragraph::new(avg_speed)
-        .style(Style::default().fg(Color::Cyan))
+/// The player (`>`) and the ghost (`G`) as two cursors on a track of the race
+/// words at `now`, with the player's lead in the title, or the outcome once
+/// finished.
+fn draw_ghost_race(f: &mut Frame, theme: &Theme, race: &GhostRace, now: Instant, area: Rect) {
+    let ghost_words = race.ghost_words(now);
+    let lead = race.player_words() as i64 - ghost_words as i64;
+    let line = match race.result().filter(|_| race.is_finished()) {
+        Some(result) => Line::styled(
+            result.summary(),
+            if result.won() {
+                theme.success()
+            } else {
+                theme.failure()
+            },
+        ),
+        None => {
+            let width = usize::from(area.width.saturating_sub(2)).max(1);
+            let position = |words: usize| words * (width - 1) / race.total_words().max(1);
+            let player = position(race.player_words());
+            let ghost = position(ghost_words);
+            let track = (0..width).map(|i| {
+                if i == player {
+                    Span::styled(">", theme.success().add_modifier(Modifier::BOLD))
+                } else if i == ghost {
+                    Span::styled("G", theme.ghost())
+                } else {
+                    Span::styled("·", Style::default().add_modifier(Modifier::DIM))
+                }
+            });
+            Line::from(track.collect::<Vec<_>>())
+        }
+    };
+    let race_paragraph = Paragraph::new(line).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!("Ghost ({lead:+} words)")),
+    );
+    f.render_widget(race_paragraph, area);
+}
+
+fn draw_session_metrics(f: &mut Frame, app: &App, area: Rect) {
+    let stats = app.session.performance.keystroke_stats();
+    let lines = vec![
+        Line::raw(format!(
+            "Net {:.2} WPM | Raw {:.2} WPM",
+            app.session.performance.net_wpm(),
+            app.session.performance.raw_wpm(),
+        )),
+        Line::raw(format!(
+            "Accuracy {:.1}% | Words {}",
+            app.session.performance.accuracy(),
+            stats.words_typed,
+        )),
+        Line::raw(format!(
+            "Errors {} corrected, {} uncorrected",
+            stats.corrected_errors, stats.uncorrected_errors,
+        )),
+    ];
+    let title = match app.seed {
+        Some(seed) => format!("Session (seed {seed})"),
+        None => "Session".to_string(),
+    };
+    let metrics_paragraph = Paragraph::new(lines)
+        .style(app.theme.stats())
+        .block(Block::default().borders(Borders::ALL).title(title));
+    f.render_widget(metrics_paragraph, area);
+}
+
+fn draw_keyboard(f: &mut Frame, app: &App, area: Rect) {
+    let toggle = app.keymap.hint(InputEvent::Command(Command::ToggleHeatmap));
+    let mode = match app.heatmap_mode {
+        HeatmapMode::ErrorRate => format!("error rate ({toggle}: latency)"),
+        HeatmapMode::Latency => format!("latency ({toggle}: error rate)"),
+    };
+    let heatmap = KeyboardHeatmap {
+        layout: &app.session.layout,
+        key_stats: app.session.performance.key_stats(),
+        mode: app.heatmap_mode,
+        next_key: app.session.next_char(),
+        theme: &app.theme,
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!("{}: {mode}", app.session.layout.name));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    f.render_widget(heatmap, inner);
+}
+
+/// The keys of a layout, coloured by how well each key is known, followed by a
+/// row with the same colouring for each finger.
+///
+/// Keys without data are dimmed, and the next key to type is highlighted
+/// regardless of its statistics.
+struct KeyboardHeatmap<'a> {
+    layout: &'a KeyboardLayout,
+    key_stats: &'a KeyStats,
+    mode: HeatmapMode,
+    next_key: Option<char>,
+    theme: &'a Theme,
+}
+
+impl KeyboardHeatmap<'_> {
+    /// Width of a key cell, in columns.
+    const KEY_WIDTH: u16 = 3;
+
+    /// Columns each row is shifted right from the row above.
+    const ROW_STAGGER: u16 = 1;
+
+    /// Width of the widget without borders.
+    fn width(layout: &KeyboardLayout) -> u16 {
+        let keys_width = layout
+            .rows
+            .iter()
+            .enumerate()
+            .map(|(index, row)| {
+                index as u16 * Self::ROW_STAGGER + row.len() as u16 * Self::KEY_WIDTH
+            })
+            .max()
+            .unwrap_or(0);
+        // The finger row has a separator between the hands.
+        let fingers_width = Finger::ALL.len() as u16 * Self::KEY_WIDTH + 2;
+        keys_width.max(fingers_width)
+    }
+
+    /// Height of the widget without borders: the key rows and the finger row.
+    fn height(layout: &KeyboardLayout) -> u16 {
+        layout.rows.len() as u16 + 1
+    }
+
+    fn key_style(&self, key: char) -> Style {
+        if self.next_key == Some(key) {
+            return self.theme.next_key();
+        }
+        self.heat_style(self.key_stats.get(key))
+    }
+
+    fn heat_style(&self, entry: Option<&KeyStatsEntry>) -> Style {
+        let dim = Style::default().add_modifier(Modifier::DIM);
+        let Some(entry) = entry else {
+            return dim;
+        };
+        let level = match self.mode {
+            HeatmapMode::ErrorRate => heat_level(entry.error_rate(), [0.02, 0.05, 0.10]),
+            HeatmapMode::Latency => match entry.mean_latency() {
+                Some(latency) => heat_level(latency.as_secs_f32(), [0.2, 0.35, 0.5]),
+                None => return dim,
+            },
+        };
+        self.theme.heat(level)
+    }
+}
+
+/// Map `value` to a heat level from 0 to 3 using ascending thresholds.
+fn heat_level(value: f32, thresholds: [f32; 3]) -> usize {
+    thresholds
+        .iter()
+        .position(|threshold| value < *threshold)
+        .unwrap_or(thresholds.len())
+}
+
+impl Widget for KeyboardHeatmap<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let mut lines: Vec<(u16, Line)> = self
+            .layout
+            .rows
+            .iter()
+            .enumerate()
+            .map(|(row_index, row)| {
+                let spans: Vec<Span> = row
+                    .iter()
+                    .map(|key| Span::styled(format!(" {} ", key.char), self.key_style(key.char)))
+                    .collect();
+                (row_index as u16 * Self::ROW_STAGGER, Line::from(spans))
+            })
+            .collect();
+
+        let finger_stats = self.key_stats.by_finger(self.layout);
+        let mut finger_spans = Vec::new();
+        for (index, finger) in Finger::ALL.iter().enumerate() {
+            if index > 0 && finger.hand() != Finger::ALL[index - 1].hand() {
+                finger_spans.push(Span::raw("  "));
+            }
+            finger_spans.push(Span::styled(
+                format!("{:^3}", finger.short_name()),
+                self.heat_style(finger_stats.get(finger)),
+            ));
+        }
+        lines.push((0, Line::from(finger_spans)));
+
+        for (row_index, (indent, line)) in lines.into_iter().enumerate() {
+            let y = area.y + row_index as u16;
+            if y >= area.bottom() {
+                break;
+            }
+            let row_area = Rect {
+                x: area.x + indent.min(area.width),
+                y,
+                width: area.width.saturating_sub(indent),
+                height: 1,
+            };
+            line.render(row_area, buf);
+        }
+    }
+}
+
+fn draw_timed_test_results(
+    f: &mut Frame,
+    app: &App,
+    test: &TimedTest,
+    race: Option<RaceResult>,
+    area: Rect,
+) {
+    let Some(results) = test.results() else {
+        return;
+    };
+    let duration = test.duration();
+    let value_style = app.theme.stats().add_modifier(Modifier::BOLD);
+    let mut lines = vec![
+        Line::from(vec![
+            Span::raw("Net speed: "),
+            Span::styled(format!("{:.2} WPM", results.net_wpm(duration)), value_style),
+        ]),
+        Line::from(vec![
+            Span::raw("Raw speed: "),
+            Span::styled(format!("{:.2} WPM", results.raw_wpm(duration)), value_style),
+        ]),
+        Line::from(vec![
+            Span::raw("Accuracy:  "),
+            Span::styled(format!("{:.1}%", results.accuracy()), value_style),
+        ]),
+        Line::from(vec![
+            Span::raw("Words:     "),
+            Span::styled(results.words_typed.to_string(), value_style),
+        ]),
+        Line::from(vec![
+            Span::raw("Errors:    "),
+            Span::styled(
+                format!(
+                    "{} corrected, {} uncorrected",
+                    results.corrected_errors, results.uncorrected_errors
+                ),
+                value_style,
+            ),
+        ]),
+    ];
+    if let Some(race) = race {
+        lines.push(Line::from(vec![
+            Span::raw("Ghost:     "),
+            Span::styled(
+                format!("{} over {} words", race.summary(), race.words),
+                value_style,
+            ),
+        ]));
+    }
+    lines.push(Line::raw(""));
+    let keys = &app.keymap;
+    lines.push(Line::from(Span::styled(
+        format!(
+            "{}: new test | {}: change level | {}: end session",
+            keys.hint(InputEvent::Command(Command::Restart)),
+            keys.hint(InputEvent::NextList),
+            keys.hint(InputEvent::Quit)
+        ),
+        Style::default().add_modifier(Modifier::DIM),
+    )));
+
+    let results_paragraph = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!("Test Results ({}s)", duration.as_secs())),
+    );
+    f.render_widget(results_paragraph, area);
+}
+
+/// Draw the results of a finished session, with a footer listing the keys to
+/// restart or change level when `can_restart` is set.
+pub(crate) fn draw_results(f: &mut Frame, app: &App, log_file: Option<&Path>, can_restart: bool) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints([
+            Constraint::Length(6),
+            Constraint::Min(8),
+            Constraint::Length(12),
+            Constraint::Length(1),
+        ])
+        .split(f.area());
+
+    draw_results_summary(f, app, log_file, chunks[0]);
+    draw_stats(f, app, chunks[1]);
+    draw_speed_chart(f, app, chunks[2]);
+
+    let restart = app.keymap.hint(InputEvent::Command(Command::Restart));
+    let quit = app.keymap.hint(InputEvent::Quit);
+    let keys = match (can_restart, app.session.word_queue.adaptive_lesson()) {
+        (false, _) => format!("{quit}: quit"),
+        (true, Some(_)) => format!("{restart}: restart | {quit}: quit"),
+        (true, None) => format!(
+            "{restart}: restart | {}/{}: change level | {quit}: quit",
+            app.keymap.hint(InputEvent::NextList),
+            app.keymap.hint(InputEvent::PrevList)
+        ),
+    };
+    let footer = Paragraph::new(keys).style(Style::default().add_modifier(Modifier::DIM));
+    f.render_widget(footer, chunks[3]);
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{}m {:02}s", secs / 60, secs % 60)
+}
+
+fn draw_results_summary(f: &mut Frame, app: &App, log_file: Option<&Path>, area: Rect) {
+    let stats = app.session.performance.keystroke_stats();
+    let value_style = app.theme.stats().add_modifier(Modifier::BOLD);
+    let mut lines = vec![
+        Line::from(vec![
+            Span::raw("Speed: "),
+            Span::styled(
+                format!("{:.2} WPM", app.session.performance.average_wpm()),
+                value_style,
+            ),
+            Span::raw(format!(
+                " (net {:.2}, raw {:.2}) | Accuracy: ",
+                app.session.performance.net_wpm(),
+                app.session.performance.raw_wpm()
+            )),
+            Span::styled(
+                format!("{:.1}%", app.session.performance.accuracy()),
+                value_style,
+            ),
+            Span::raw(" | Time: "),
+            Span::styled(
+                format_duration(app.session.performance.typing_duration()),
+                value_style,
+            ),
+        ]),
+        Line::raw(format!(
+            "Words: {} | Errors: {} corrected, {} uncorrected",
+            stats.words_typed, stats.corrected_errors, stats.uncorrected_errors
+        )),
+    ];
+    if let Some(result) = app.ghost_race.as_ref().and_then(GhostRace::result) {
+        lines.push(Line::raw(format!(
+            "Ghost: {} over {} words",
+            result.summary(),
+            result.words
+        )));
+    }
+    let mut session = Vec::new();
+    if let Some(seed) = app.seed {
+        session.push(format!("Seed: {seed}"));
+    }
+    if let Some(path) = log_file {
+        session.push(format!("Log: {}", path.display()));
+    }
+    lines.push(Line::styled(
+        session.join(" | "),
+        Style::default().add_modifier(Modifier::DIM),
+    ));
+
+    let summary = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!("Session Results: {}", app.level_name())),
+    );
+    f.render_widget(summary, area);
+}
+
+/// Number of words averaged by the trend line of the speed chart.
+const SPEED_TREND_WORDS: usize = 10;
+
+/// The speed of each word over the session, as dots, with a line following
+/// the average of the last few words.
+fn draw_speed_chart(f: &mut Frame, app: &App, area: Rect) {
+    let timeline = app.session.performance.speed_timeline();
+    let words: Vec<(f64, f64)> = timeline
+        .iter()
+        .map(|(at, speed)| (at.as_secs_f64(), f64::from(*speed)))
+        .collect();
+    let trend: Vec<(f64, f64)> = (0..words.len())
+        .map(|i| {
+            let window = &words[(i + 1).saturating_sub(SPEED_TREND_WORDS)..=i];
+            let mean = window.iter().map(|(_, speed)| speed).sum::<f64>() / window.len() as f64;
+            (words[i].0, mean)
+        })
+        .collect();
+
+    let max_secs = words.last().map_or(0.0, |(at, _)| *at).max(1.0);
+    let max_speed = words.iter().map(|(_, speed)| *speed).fold(10.0, f64::max);
+    let max_speed = (max_speed / 10.0).ceil() * 10.0;
+    let datasets = vec![
+        Dataset::default()
+            .name("word")
+            .marker(Marker::Dot)
+            .graph_type(GraphType::Scatter)
+            .style(Style::default().add_modifier(Modifier::DIM))
+            .data(&words),
+        Dataset::default()
+            .name(format!("last {SPEED_TREND_WORDS} words"))
+            .marker(Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(app.theme.stats())
+            .data(&trend),
+    ];
+    let chart = Chart::new(datasets)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .title("Avg Speed (Last 10 Words)"),
+                .title("WPM over Time"),
+        )
+        .x_axis(
+            Axis::default()
+                .bounds([0.0, max_secs])
+                .labels(["0s".to_string(), format!("{max_secs:.0}s")]),
+        )
+        .y_axis(
+            Axis::default()
+                .bounds([0.0, max_speed])
+                .labels(["0".to_string(), format!("{max_speed:.0}")]),
         );
-    f.render_widget(avg_speed_paragraph, typing_area[1]);
-    let styled_input = generate_styled_input(&app.user_input, app.performance.mistyped_chars());
-    let user_input = Paragraph::new(Line::from(styled_input))
-        .style(Style::default().fg(Color::Green))
-        .block(Block::default().borders(Borders::ALL).title("Your Input"));
-    f.render_widget(user_input, input_chunk);
+    f.render_widget(chart, area);
 }
 
 fn draw_stats(f: &mut Frame, app: &App, area: Rect) {
@@ -156,6 +735,7 @@ fn draw_stats(f: &mut Frame, app: &App, area: Rect) {
 
 fn draw_fastest_words(f: &mut Frame, app: &App, area: Rect) {
     let fastest_words: Vec<ListItem> = app
+        .session
         .performance
         .get_fastest_words()
         .iter()
@@ -177,6 +757,7 @@ fn draw_fastest_words(f: &mut Frame, app: &App, area: Rect) {
 
 fn draw_slowest_words(f: &mut Frame, app: &App, area: Rect) {
     let slowest_words: Vec<ListItem> = app
+        .session
         .performance
         .get_slowest_words()
         .iter()
@@ -198,6 +779,7 @@ fn draw_slowest_words(f: &mut Frame, app: &App, area: Rect) {
 
 fn draw_problem_words(f: &mut Frame, app: &App, area: Rect) {
     let problem_words: Vec<ListItem> = app
+        .session
         .performance
         .get_problem_words()
         .iter()
@@ -220,13 +802,17 @@ fn draw_problem_words(f: &mut Frame, app: &App, area: Rect) {
 
 fn draw_struggle_combinations(f: &mut Frame, app: &App, area: Rect) {
     let struggle_combinations: Vec<ListItem> = app
+        .session
         .performance
         .get_struggle_combinations()
         .iter()
         .take(20)
-        .map(|(combo, speed)| {
+        .map(|stats| {
             ListItem::new(Line::from(vec![Span::raw(format!(
-                "{combo}: {speed:.2} WPM"
+                "{}: {:.2} WPM ({}x)",
+                stats.combination,
+                stats.speed(),
+                stats.samples
             ))]))
         })
         .collect();
@@ -238,3 +824,201 @@ fn draw_struggle_combinations(f: &mut Frame, app: &App, area: Rect) {
     );
     f.render_widget(struggle_combinations_list, area);
 }
+
+#[cfg(test)]
+mod tests {
+    //! Snapshots of the screens of scripted sessions.
+    //!
+    //! Each snapshot holds the text of the rendered buffer followed by its
+    //! styled cells, one run of equal style per line. Run the tests with
+    //! `UPDATE_SNAPSHOTS=1` to write the snapshots after a deliberate change
+    //! of the interface, and review them like any other change.
+
+    use std::fmt::Write as _;
+    use std::fs;
+    use std::path::PathBuf;
+
+    use ratatui::Terminal;
+    use ratatui::backend::TestBackend;
+    use ratatui::buffer::{Buffer, Cell};
+    use ratatui::style::Color;
+
+    use super::*;
+    use crate::app::AppOptions;
+    use crate::clock::ManualClock;
+    use crate::input::{InputEvent, Keymap};
+
+    /// The text of `buffer`, then the runs of cells with a style of their own.
+    fn snapshot_of(buffer: &Buffer) -> String {
+        let width = buffer.area.width as usize;
+        let rows: Vec<&[Cell]> = buffer.content().chunks(width).collect();
+        let mut snapshot = String::new();
+        for row in &rows {
+            let text: String = row.iter().map(Cell::symbol).collect();
+            writeln!(snapshot, "{}", text.trim_end()).unwrap();
+        }
+        snapshot.push_str("--- styles ---\n");
+        for (y, row) in rows.iter().enumerate() {
+            let mut x = 0;
+            while x < row.len() {
+                let style = style_of(&row[x]);
+                let end = x + row[x..]
+                    .iter()
+                    .take_while(|cell| style_of(cell) == style)
+                    .count();
+                if !style.is_empty() {
+                    writeln!(snapshot, "{y:>3} {x:>3}..{end:<3} {style}").unwrap();
+                }
+                x = end;
+            }
+        }
+        snapshot
+    }
+
+    /// The colours and modifiers of `cell`, empty for the default style.
+    fn style_of(cell: &Cell) -> String {
+        let mut parts = Vec::new();
+        if cell.fg != Color::Reset {
+            parts.push(format!("fg:{:?}", cell.fg));
+        }
+        if cell.bg != Color::Reset {
+            parts.push(format!("bg:{:?}", cell.bg));
+        }
+        if !cell.modifier.is_empty() {
+            parts.push(format!("{:?}", cell.modifier));
+        }
+        parts.join(" ")
+    }
+
+    /// Compare `buffer` with the snapshot `name`, or write the snapshot when
+    /// `UPDATE_SNAPSHOTS` is set.
+    fn assert_snapshot(name: &str, buffer: &Buffer) {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("src/snapshots")
+            .join(format!("{name}.snap"));
+        let actual = snapshot_of(buffer);
+        if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, actual).unwrap();
+            return;
+        }
+        let expected = fs::read_to_string(&path).unwrap_or_else(|_| {
+            panic!(
+                "missing snapshot {}, run the tests with UPDATE_SNAPSHOTS=1 to write it",
+                path.display()
+            )
+        });
+        if actual != expected {
+            let line = actual
+                .lines()
+                .zip(expected.lines())
+                .position(|(actual, expected)| actual != expected)
+                .unwrap_or_else(|| actual.lines().count().min(expected.lines().count()));
+            panic!(
+                "snapshot {name} differs from line {}, run the tests with UPDATE_SNAPSHOTS=1 \
+                 to update it\n--- expected\n{expected}\n--- actual\n{actual}",
+                line + 1
+            );
+        }
+    }
+
+    fn render(width: u16, height: u16, draw: impl FnOnce(&mut Frame)) -> Buffer {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(draw).unwrap();
+        terminal.backend().buffer().clone()
+    }
+
+    /// Type `text` one key every `step`, a space submitting the word.
+    fn type_text(app: &mut App, clock: &ManualClock, text: &str, step: Duration) {
+        for c in text.chars() {
+            app.on_event(InputEvent::typed(c));
+            clock.advance(step);
+        }
+    }
+
+    /// A seeded session with a few words typed at different speeds, a failed
+    /// word repeated as a problem word and a mistyped character in the input.
+    fn scripted_app() -> App {
+        let clock = ManualClock::new();
+        let mut app = App::new(AppOptions {
+            seed: Some(42),
+            ..AppOptions::default()
+        })
+        .unwrap()
+        .with_clock(clock.clone());
+        for step in [120, 180, 150, 240, 200] {
+            let word = format!("{} ", app.session.word_queue.current_word());
+            type_text(&mut app, &clock, &word, Duration::from_millis(step));
+        }
+        let word = app.session.word_queue.current_word().to_string();
+        let mut wrong: String = word.chars().rev().collect();
+        wrong.push(' ');
+        type_text(&mut app, &clock, &wrong, Duration::from_millis(300));
+
+        assert!(app.session.word_queue.is_current_word_problem());
+        let first = app
+            .session
+            .word_queue
+            .current_word()
+            .chars()
+            .next()
+            .unwrap();
+        let mistyped = if first == 'x' { 'q' } else { 'x' };
+        type_text(
+            &mut app,
+            &clock,
+            &format!("{first}{mistyped}"),
+            Duration::from_millis(150),
+        );
+        app
+    }
+
+    #[test]
+    fn test_typing_screen_sizes() {
+        let app = scripted_app();
+        for (width, height) in [(120, 40), (100, 30), (80, 24)] {
+            let buffer = render(width, height, |f| draw(f, &app));
+            assert_snapshot(&format!("typing_{width}x{height}"), &buffer);
+        }
+    }
+
+    #[test]
+    fn test_typing_screen_monochrome() {
+        let mut app = scripted_app();
+        app.theme = Theme::monochrome();
+        let buffer = render(100, 30, |f| draw(f, &app));
+        assert_snapshot("typing_monochrome_100x30", &buffer);
+    }
+
+    #[test]
+    fn test_results_screen() {
+        let app = scripted_app();
+        let buffer = render(100, 40, |f| draw_results(f, &app, None, true));
+        assert_snapshot("results_100x40", &buffer);
+    }
+
+    #[test]
+    fn test_next_session_changes_are_shown() {
+        let mut app = scripted_app();
+        app.next_session_changes = vec!["level", "time"];
+        let buffer = render(100, 30, |f| draw(f, &app));
+        let title = snapshot_of(&buffer).lines().nth(1).unwrap().to_string();
+        assert!(
+            title.contains("Word Lists level, time changed: applies from the next session"),
+            "{title}"
+        );
+    }
+
+    #[test]
+    fn test_results_footer_follows_the_keymap() {
+        let mut app = scripted_app();
+        let bindings = toml::from_str("quit = \"ctrl+q\"\nrestart = \"f5\"").unwrap();
+        app.keymap = Keymap::new(bindings).unwrap();
+        let buffer = render(100, 40, |f| draw_results(f, &app, None, true));
+        let footer = snapshot_of(&buffer).lines().nth(38).unwrap().to_string();
+        assert_eq!(
+            footer,
+            " F5: restart | Tab/Shift+Tab: change level | Ctrl+Q: quit"
+        );
+    }
+}
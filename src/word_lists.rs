@@ -3,9 +3,17 @@
 //! This module provides access to compressed word lists organized by Dvorak keyboard
 //! learning progression. Each level introduces additional keys, building from home row
 //! to full alphabet mastery.
+//!
+//! User-supplied lists are read from plain `.txt` or gzipped `.txt.gz` files
+//! with one word per line, and are appended after the embedded levels. Lines
+//! holding several words are split on whitespace, since Space submits a word
+//! and a word containing one could never be typed.
 
-use std::io;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
 
+use anyhow::{Context, Result, bail};
 use flate2::read::GzDecoder;
 
 const LEVEL1_GZIP: &[u8] = include_bytes!("../lessons/level1.txt.gz");
@@ -25,38 +33,209 @@ pub(crate) struct WordList {
     pub(crate) words: Vec<String>,
 }
 
-pub(crate) fn load_word_lists() -> Vec<WordList> {
-    vec![
+/// Load the embedded lessons followed by the lists found at `user_paths`.
+///
+/// Each path is either a word list file or a directory whose `.txt` and
+/// `.txt.gz` files are loaded in name order.
+pub(crate) fn load_word_lists(user_paths: &[PathBuf]) -> Result<Vec<WordList>> {
+    let mut word_lists = load_embedded_word_lists()?;
+    for path in user_paths {
+        word_lists.extend(load_user_word_lists(path)?);
+    }
+    Ok(word_lists)
+}
+
+fn load_embedded_word_lists() -> Result<Vec<WordList>> {
+    Ok(vec![
         WordList {
             name: "Home Row - 8 keys".to_string(),
-            words: load_words_from_embedded(LEVEL1_GZIP),
+            words: load_words_from_embedded(LEVEL1_GZIP)?,
         },
         WordList {
             name: "Home Row - 10 keys".to_string(),
-            words: load_words_from_embedded(LEVEL2_GZIP),
+            words: load_words_from_embedded(LEVEL2_GZIP)?,
         },
         WordList {
             name: "Home Row + 8 keys".to_string(),
-            words: load_words_from_embedded(LEVEL3_GZIP),
+            words: load_words_from_embedded(LEVEL3_GZIP)?,
         },
         WordList {
             name: "Home Row + 8 more keys".to_string(),
-            words: load_words_from_embedded(LEVEL4_GZIP),
+            words: load_words_from_embedded(LEVEL4_GZIP)?,
         },
         WordList {
             name: "Full Alphabet".to_string(),
-            words: load_words_from_embedded(LEVEL5_GZIP),
+            words: load_words_from_embedded(LEVEL5_GZIP)?,
         },
-    ]
+    ])
 }
 
-fn load_words_from_embedded(source: &[u8]) -> Vec<String> {
-    decompress_gzip(source).lines().map(str::to_owned).collect()
+/// The directory scanned for user word lists, inside the user config directory.
+pub(crate) fn user_lessons_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("dvoratt").join("lessons"))
 }
 
-fn decompress_gzip(compressed_data: &[u8]) -> String {
+fn load_user_word_lists(path: &Path) -> Result<Vec<WordList>> {
+    if !path.is_dir() {
+        return Ok(vec![load_word_list_file(path)?]);
+    }
+
+    let entries =
+        fs::read_dir(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let mut files = Vec::new();
+    for entry in entries {
+        let entry_path = entry
+            .with_context(|| format!("failed to read {}", path.display()))?
+            .path();
+        if entry_path.is_file() && list_name(&entry_path).is_some() {
+            files.push(entry_path);
+        }
+    }
+    files.sort();
+    files.iter().map(|file| load_word_list_file(file)).collect()
+}
+
+pub(crate) fn load_word_list_file(path: &Path) -> Result<WordList> {
+    let Some(name) = list_name(path) else {
+        bail!(
+            "{} is not a word list, expected a .txt or .txt.gz file",
+            path.display()
+        );
+    };
+    let bytes = fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let contents = if path.extension().is_some_and(|ext| ext == "gz") {
+        decompress_gzip(&bytes)
+    } else {
+        String::from_utf8(bytes).context("content is not valid UTF-8")
+    }
+    .with_context(|| format!("failed to load word list {}", path.display()))?;
+
+    let words: Vec<String> = contents.split_whitespace().map(str::to_owned).collect();
+    if words.is_empty() {
+        bail!("word list {} contains no words", path.display());
+    }
+    Ok(WordList { name, words })
+}
+
+/// The list name derived from a `.txt` or `.txt.gz` file name.
+fn list_name(path: &Path) -> Option<String> {
+    let file_name = path.file_name()?.to_str()?;
+    file_name
+        .strip_suffix(".txt.gz")
+        .or_else(|| file_name.strip_suffix(".txt"))
+        .filter(|stem| !stem.is_empty())
+        .map(str::to_owned)
+}
+
+fn load_words_from_embedded(source: &[u8]) -> Result<Vec<String>> {
+    Ok(decompress_gzip(source)?
+        .lines()
+        .map(str::to_owned)
+        .collect())
+}
+
+fn decompress_gzip(compressed_data: &[u8]) -> Result<String> {
     let mut decoder = GzDecoder::new(compressed_data);
     let mut decoded_bytes = Vec::new();
-    io::copy(&mut decoder, &mut decoded_bytes).expect("Failed to decompress");
-    String::from_utf8(decoded_bytes).expect("Decoded bytes are not valid UTF-8")
+    decoder
+        .read_to_end(&mut decoded_bytes)
+        .context("failed to decompress gzip data")?;
+    String::from_utf8(decoded_bytes).context("decompressed content is not valid UTF-8")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_load_embedded_word_lists() {
+        let word_lists = load_word_lists(&[]).unwrap();
+        assert_eq!(word_lists.len(), 5);
+        assert!(word_lists.iter().all(|list| !list.words.is_empty()));
+    }
+
+    #[test]
+    fn test_load_plain_and_gzip_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let plain = dir.path().join("animals.txt");
+        let gzipped = dir.path().join("colors.txt.gz");
+        fs::write(&plain, "cat\n\n  dog \n").unwrap();
+        fs::write(&gzipped, gzip(b"red\nblue\n")).unwrap();
+
+        let word_lists = load_word_lists(&[plain, gzipped]).unwrap();
+        assert_eq!(word_lists.len(), 7);
+        assert_eq!(word_lists[5].name, "animals");
+        assert_eq!(word_lists[5].words, ["cat", "dog"]);
+        assert_eq!(word_lists[6].name, "colors");
+        assert_eq!(word_lists[6].words, ["red", "blue"]);
+    }
+
+    #[test]
+    fn test_lines_are_split_into_words() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("food.txt");
+        fs::write(
+            &path,
+            "ice cream
+tea	cake
+",
+        )
+        .unwrap();
+
+        let word_list = load_word_list_file(&path).unwrap();
+        assert_eq!(word_list.words, ["ice", "cream", "tea", "cake"]);
+    }
+
+    #[test]
+    fn test_load_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("b.txt"), "bee\n").unwrap();
+        fs::write(dir.path().join("a.txt.gz"), gzip(b"ant\n")).unwrap();
+        fs::write(dir.path().join("notes.md"), "ignored").unwrap();
+
+        let word_lists = load_user_word_lists(dir.path()).unwrap();
+        let names: Vec<&str> = word_lists.iter().map(|l| l.name.as_str()).collect();
+        assert_eq!(names, ["a", "b"]);
+    }
+
+    #[test]
+    fn test_invalid_utf8_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bad.txt");
+        fs::write(&path, [0xff, 0xfe, b'\n']).unwrap();
+
+        let err = load_word_list_file(&path).unwrap_err();
+        assert!(format!("{err:#}").contains("not valid UTF-8"));
+    }
+
+    #[test]
+    fn test_malformed_gzip_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bad.txt.gz");
+        fs::write(&path, b"not gzip").unwrap();
+
+        let err = load_word_list_file(&path).unwrap_err();
+        assert!(format!("{err:#}").contains("failed to decompress"));
+    }
+
+    #[test]
+    fn test_empty_and_unsupported_files_are_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let empty = dir.path().join("empty.txt");
+        let other = dir.path().join("words.csv");
+        fs::write(&empty, "\n\n").unwrap();
+        fs::write(&other, "word").unwrap();
+
+        assert!(load_word_list_file(&empty).is_err());
+        assert!(load_word_list_file(&other).is_err());
+    }
 }
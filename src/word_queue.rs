@@ -5,18 +5,27 @@
 //! - Cycling through word lists
 //! - Maintaining a buffer of upcoming words
 //! - Random shuffling of words between cycles
+//! - Words picked on the fly by an adaptive lesson
+//! - Spacing out words due for review from earlier sessions
 
-use rand::rng;
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
+use rand::{SeedableRng, make_rng};
 use std::collections::VecDeque;
 
+use crate::adaptive::AdaptiveLesson;
+use crate::performance::{KeyStats, MasteryPolicy};
+
+/// Number of new words between two review words.
+const REVIEW_SPACING: usize = 4;
+
 /// Manages the queue of words to type during practice sessions.
 ///
 /// Handles:
 /// - Current word display
 /// - Next words preview
-/// - Problem word repetition (words repeated up to 3 times)
-/// - Word list cycling and shuffling
+/// - Problem word repetition (words repeated 3 times unless configured otherwise)
+/// - Word list cycling and shuffling, or words from an adaptive lesson
 /// - Switching between different difficulty levels
 pub(crate) struct WordQueue {
     problem_word_queue: VecDeque<(String, u8)>,
@@ -26,6 +35,17 @@ pub(crate) struct WordQueue {
     next_words: Vec<String>,
     is_repeating_problem_word: bool,
     problem_word_repetitions: u8,
+    /// Position of the current word among the new words of the queue, which
+    /// problem word repetitions do not advance
+    word_index: usize,
+    /// Times a problem word is typed before moving on
+    required_repetitions: u8,
+    /// Source of new words replacing the word list, in adaptive mode
+    adaptive_lesson: Option<AdaptiveLesson>,
+    /// Words due for review, mixed in among the new words
+    review_words: VecDeque<String>,
+    words_since_review: usize,
+    rng: StdRng,
 }
 
 impl WordQueue {
@@ -37,29 +57,101 @@ impl WordQueue {
         self.problem_word_repetitions
     }
 
-    pub(crate) fn new(initial_words: Vec<String>) -> Self {
+    /// Type each problem word `repetitions` times before moving on.
+    pub(crate) fn set_required_repetitions(&mut self, repetitions: u8) {
+        self.required_repetitions = repetitions;
+    }
+
+    /// Create a queue over `initial_words`.
+    ///
+    /// With a `seed`, the word order is fully determined by it; otherwise the
+    /// order is random.
+    pub(crate) fn new(initial_words: Vec<String>, seed: Option<u64>) -> Self {
+        Self::with_source(initial_words, None, seed)
+    }
+
+    /// Create a queue whose words are picked by `lesson`.
+    pub(crate) fn adaptive(lesson: AdaptiveLesson, seed: Option<u64>) -> Self {
+        Self::with_source(Vec::new(), Some(lesson), seed)
+    }
+
+    fn with_source(
+        initial_words: Vec<String>,
+        adaptive_lesson: Option<AdaptiveLesson>,
+        seed: Option<u64>,
+    ) -> Self {
+        let mut rng = seed.map_or_else(make_rng, StdRng::seed_from_u64);
         let original_words = initial_words.clone();
         let mut all_words = initial_words;
-        all_words.shuffle(&mut rng());
-        let current_word = all_words.pop().unwrap_or_default();
-        let next_words: Vec<String> = (0..2)
-            .map(|_| all_words.pop().unwrap_or_default())
-            .collect();
+        all_words.shuffle(&mut rng);
 
-        WordQueue {
+        let mut queue = WordQueue {
             problem_word_queue: VecDeque::new(),
             original_words,
             all_words,
-            current_word,
-            next_words,
+            current_word: String::new(),
+            next_words: Vec::new(),
             is_repeating_problem_word: false,
             problem_word_repetitions: 0,
+            word_index: 0,
+            required_repetitions: MasteryPolicy::default().repetitions,
+            adaptive_lesson,
+            review_words: VecDeque::new(),
+            words_since_review: 0,
+            rng,
+        };
+        queue.current_word = queue.draw_word();
+        queue.next_words = (0..2).map(|_| queue.draw_word()).collect();
+        queue
+    }
+
+    /// The next new word: a review word every [`REVIEW_SPACING`] words, then
+    /// a word picked by the adaptive lesson, or else the next word of the
+    /// shuffled list, reshuffling it once exhausted.
+    fn draw_word(&mut self) -> String {
+        self.words_since_review += 1;
+        if self.words_since_review > REVIEW_SPACING
+            && let Some(word) = self.review_words.pop_front()
+        {
+            self.words_since_review = 0;
+            return word;
+        }
+        if let Some(lesson) = &self.adaptive_lesson {
+            return lesson.generate_word(&mut self.rng);
+        }
+        if self.all_words.is_empty() {
+            self.all_words = self.original_words.clone();
+            self.all_words.shuffle(&mut self.rng);
+        }
+        self.all_words.pop().unwrap_or_default()
+    }
+
+    /// Mix words due for review into the queue.
+    ///
+    /// The first one replaces the current word, so that reviews start right
+    /// away, and the others follow every few words.
+    pub(crate) fn add_review_words(&mut self, words: Vec<String>) {
+        self.review_words.extend(words);
+        if let Some(word) = self.review_words.pop_front() {
+            self.current_word = word;
+            self.words_since_review = 0;
         }
     }
 
+    pub(crate) fn adaptive_lesson(&self) -> Option<&AdaptiveLesson> {
+        self.adaptive_lesson.as_ref()
+    }
+
+    /// Let the adaptive lesson, if any, react to the latest key statistics.
+    ///
+    /// Returns the newly unlocked key, if any.
+    pub(crate) fn update_adaptive_lesson(&mut self, stats: &KeyStats) -> Option<char> {
+        self.adaptive_lesson.as_mut()?.update(stats)
+    }
+
     pub(crate) fn next_word(&mut self) {
         if self.is_repeating_problem_word {
-            if self.problem_word_repetitions >= 3 {
+            if self.problem_word_repetitions >= self.required_repetitions {
                 self.is_repeating_problem_word = false;
                 self.problem_word_repetitions = 0;
                 self.problem_word_queue.pop_front();
@@ -68,27 +160,18 @@ impl WordQueue {
             }
         }
 
-        if self.next_words.is_empty() {
-            self.next_words = self
-                .all_words
-                .split_off(self.all_words.len().saturating_sub(2));
-        }
-
         if let Some((problem_word, _)) = self.problem_word_queue.front() {
             self.current_word = problem_word.clone();
             self.is_repeating_problem_word = true;
             self.problem_word_repetitions = 0;
         } else {
             self.current_word = self.next_words.remove(0);
+            self.word_index += 1;
         }
 
         while self.next_words.len() < 2 {
-            if self.all_words.is_empty() {
-                self.all_words = self.original_words.clone();
-                self.all_words.shuffle(&mut rng());
-            }
-            self.next_words
-                .push(self.all_words.pop().unwrap_or_default());
+            let word = self.draw_word();
+            self.next_words.push(word);
         }
     }
 
@@ -112,6 +195,13 @@ impl WordQueue {
         &self.current_word
     }
 
+    /// Position of the current word in the sequence of new words, the same
+    /// for every session with the same seed and word list. A problem word
+    /// keeps the position of the new word it interrupts.
+    pub(crate) fn word_index(&self) -> usize {
+        self.word_index
+    }
+
     pub(crate) fn next_words(&self) -> &[String] {
         &self.next_words
     }
@@ -119,32 +209,43 @@ impl WordQueue {
     pub(crate) fn change_word_list(&mut self, new_words: Vec<String>) {
         self.original_words = new_words.clone();
         self.all_words = new_words;
-        self.all_words.shuffle(&mut rng());
+        self.all_words.shuffle(&mut self.rng);
 
         self.problem_word_queue.clear();
         self.is_repeating_problem_word = false;
         self.problem_word_repetitions = 0;
+        self.word_index = 0;
 
-        self.next_words.clear();
-        self.next_words = self
-            .all_words
-            .split_off(self.all_words.len().saturating_sub(2));
-        self.current_word = self.all_words.pop().unwrap_or_default();
+        self.current_word = self.draw_word();
+        self.next_words = (0..2).map(|_| self.draw_word()).collect();
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::keyboard_layout::KeyboardLayout;
 
     #[test]
     fn test_new_word_queue() {
         let words = vec!["hello".to_string(), "world".to_string(), "rust".to_string()];
-        let queue = WordQueue::new(words);
+        let queue = WordQueue::new(words, None);
         assert!(!queue.current_word().is_empty());
         assert_eq!(queue.next_words().len(), 2);
     }
 
+    #[test]
+    fn test_seeded_word_order() {
+        let words: Vec<String> = (0..20).map(|i| format!("word{i}")).collect();
+        let mut a = WordQueue::new(words.clone(), Some(7));
+        let mut b = WordQueue::new(words, Some(7));
+        for _ in 0..30 {
+            assert_eq!(a.current_word(), b.current_word());
+            a.next_word();
+            b.next_word();
+        }
+    }
+
     #[test]
     fn test_next_word() {
         let words = vec![
@@ -153,7 +254,7 @@ mod tests {
             "third".to_string(),
             "fourth".to_string(),
         ];
-        let mut queue = WordQueue::new(words);
+        let mut queue = WordQueue::new(words, None);
         let first_word = queue.current_word().to_string();
 
         queue.next_word();
@@ -166,7 +267,7 @@ mod tests {
     #[test]
     fn test_add_problem_word() {
         let words = vec!["hello".to_string(), "world".to_string()];
-        let mut queue = WordQueue::new(words);
+        let mut queue = WordQueue::new(words, None);
 
         queue.add_problem_word("problem".to_string());
         assert!(queue.is_current_word_problem());
@@ -176,17 +277,74 @@ mod tests {
     #[test]
     fn test_update_problem_word_correct_attempt() {
         let words = vec!["hello".to_string(), "world".to_string()];
-        let mut queue = WordQueue::new(words);
+        let mut queue = WordQueue::new(words, None);
 
         queue.add_problem_word("problem".to_string());
         queue.update_problem_word_correct_attempt();
         assert_eq!(queue.get_current_problem_word_repetitions(), 1);
     }
 
+    #[test]
+    fn test_required_repetitions() {
+        let words = vec!["hello".to_string(), "world".to_string()];
+        let mut queue = WordQueue::new(words, None);
+        queue.set_required_repetitions(1);
+
+        queue.add_problem_word("problem".to_string());
+        queue.next_word();
+        assert_eq!(queue.word_index(), 0);
+        queue.update_problem_word_correct_attempt();
+        queue.next_word();
+        assert!(!queue.is_current_word_problem());
+        assert_ne!(queue.current_word(), "problem");
+        assert_eq!(queue.word_index(), 1);
+    }
+
+    #[test]
+    fn test_adaptive_words() {
+        let words = vec!["tide".to_string(), "the".to_string(), "dog".to_string()];
+        let lesson = AdaptiveLesson::new(&KeyboardLayout::default(), words);
+        let mut queue = WordQueue::adaptive(lesson, Some(5));
+        for _ in 0..20 {
+            assert_ne!(queue.current_word(), "dog");
+            queue.next_word();
+        }
+
+        let mut stats = KeyStats::default();
+        stats.record('u', true, Some(std::time::Duration::from_millis(200)));
+        assert_eq!(queue.update_adaptive_lesson(&stats), None);
+        assert_eq!(queue.adaptive_lesson().unwrap().weakest_key(), Some('i'));
+        assert_eq!(
+            WordQueue::new(Vec::new(), None).update_adaptive_lesson(&stats),
+            None
+        );
+    }
+
+    #[test]
+    fn test_review_words_are_spaced_out() {
+        let words: Vec<String> = (0..20).map(|i| format!("word{i}")).collect();
+        let mut queue = WordQueue::new(words, Some(3));
+        queue.add_review_words(vec!["heed".to_string(), "thud".to_string()]);
+        assert_eq!(queue.current_word(), "heed");
+
+        let mut typed = Vec::new();
+        for _ in 0..10 {
+            queue.next_word();
+            typed.push(queue.current_word().to_string());
+        }
+        let position = typed.iter().position(|word| word == "thud").unwrap();
+        // After the two words already previewed and REVIEW_SPACING new words.
+        assert_eq!(position, 2 + REVIEW_SPACING);
+        assert_eq!(
+            typed.iter().filter(|word| word.as_str() == "thud").count(),
+            1
+        );
+    }
+
     #[test]
     fn test_change_word_list() {
         let words = vec!["hello".to_string(), "world".to_string()];
-        let mut queue = WordQueue::new(words);
+        let mut queue = WordQueue::new(words, None);
 
         let new_words = vec!["rust".to_string(), "test".to_string(), "code".to_string()];
         queue.change_word_list(new_words.clone());
@@ -196,4 +354,15 @@ mod tests {
         assert_eq!(queue.next_words().len(), 2);
         assert!(!queue.is_current_word_problem());
     }
+
+    #[test]
+    fn test_change_to_one_word_list() {
+        let mut queue = WordQueue::new(vec!["hello".to_string(), "world".to_string()], None);
+        queue.change_word_list(vec!["tea".to_string()]);
+        for _ in 0..5 {
+            assert_eq!(queue.current_word(), "tea");
+            assert_eq!(queue.next_words(), ["tea", "tea"]);
+            queue.next_word();
+        }
+    }
 }